@@ -0,0 +1,168 @@
+use serde::Serialize;
+
+/// Category of an error, lets the frontend offer a retry or a specific remedy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// The server could not be reached or the connection dropped
+    Network,
+    /// The server answered 429 Too Many Requests
+    RateLimited,
+    /// The server answered with another error status
+    Http,
+    /// The server answered with data that could not be parsed
+    InvalidResponse,
+    /// No space left on the disk
+    DiskFull,
+    /// Reading or writing a file failed
+    Io,
+    /// The pack archive is corrupted, unsupported or over the extraction limits
+    InvalidArchive,
+    /// A simfile could not be converted to .osu
+    Conversion,
+    /// The settings could not be read or are invalid
+    Settings,
+    /// A command argument is invalid
+    InvalidInput,
+    /// The pack, file or queue entry does not exist
+    NotFound,
+    /// The operation conflicts with one already running
+    Conflict,
+    /// The download was cancelled
+    Cancelled,
+    /// The download was paused
+    Paused,
+}
+
+/// Error returned by every command, serialised as `{ kind, message, details?, fields? }`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AppError {
+    pub kind: ErrorKind,
+    pub message: String,
+    /// Extra context for inspection, such as the file or URL involved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+    /// Invalid fields of a form, such as the settings
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldError>,
+}
+
+/// Why the value of a single field was refused
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        FieldError {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl AppError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        AppError {
+            kind,
+            message: message.into(),
+            details: None,
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn with_details(mut self, details: impl Into<String>) -> Self {
+        self.details = Some(details.into());
+        self
+    }
+
+    pub fn with_fields(mut self, fields: Vec<FieldError>) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    /// Wraps an I/O error under the given context, a full disk gets its own kind
+    pub fn io(context: impl std::fmt::Display, error: std::io::Error) -> Self {
+        let kind = if error.kind() == std::io::ErrorKind::StorageFull {
+            ErrorKind::DiskFull
+        } else {
+            ErrorKind::Io
+        };
+        AppError::new(kind, format!("{}: {}", context, error))
+    }
+
+    /// Wraps a request that failed before an HTTP status was received
+    pub fn network(context: impl std::fmt::Display, error: reqwest::Error) -> Self {
+        AppError::new(ErrorKind::Network, format!("{}: {}", context, error))
+    }
+
+    /// Error for a non-success HTTP status, 429 is reported as rate limiting
+    pub fn http(status: reqwest::StatusCode) -> Self {
+        let kind = if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            ErrorKind::RateLimited
+        } else {
+            ErrorKind::Http
+        };
+        AppError::new(kind, format!("HTTP error: {}", status))
+    }
+
+    pub fn cancelled() -> Self {
+        AppError::new(ErrorKind::Cancelled, "Download cancelled")
+    }
+
+    pub fn paused() -> Self {
+        AppError::new(ErrorKind::Paused, "Download paused")
+    }
+
+    pub fn is(&self, kind: ErrorKind) -> bool {
+        self.kind == kind
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<crate::maps::ConversionError> for AppError {
+    fn from(error: crate::maps::ConversionError) -> Self {
+        let stage = match error {
+            crate::maps::ConversionError::Decode(_) => "decode",
+            crate::maps::ConversionError::Encode(_) => "encode",
+        };
+        AppError::new(ErrorKind::Conversion, error.to_string()).with_details(stage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_serialise_with_their_kind() {
+        let error = AppError::http(reqwest::StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "kind": "rate_limited",
+                "message": "HTTP error: 429 Too Many Requests"
+            })
+        );
+
+        let error = AppError::io(
+            "Error writing chart.osu",
+            std::io::Error::from(std::io::ErrorKind::StorageFull),
+        )
+        .with_details("Song/chart.osu");
+        assert_eq!(error.kind, ErrorKind::DiskFull);
+        assert_eq!(
+            serde_json::to_value(&error).unwrap()["details"],
+            "Song/chart.osu"
+        );
+    }
+}
@@ -1,6 +1,14 @@
-pub mod packs;
+pub mod error;
+pub mod library;
+pub mod logging;
 pub mod maps;
+pub mod packs;
+pub mod paths;
 pub mod settings;
+#[cfg(test)]
+mod test_utils;
+
+use tauri::Manager;
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command]
@@ -13,13 +21,49 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
+        .setup(|app| {
+            paths::init(app.handle())?;
+            let log_dir = logging::log_dir(app.handle())?;
+            let settings = settings::Settings::load().unwrap_or_default();
+            if let Err(e) = logging::init(&log_dir, settings.log_level) {
+                eprintln!("Logging disabled: {}", e);
+            }
+
+            // A broken API URL in the settings file falls back to the public API
+            let client = packs::client::EtternaOnlineClient::new(settings.client_config())
+                .or_else(|e| {
+                    tracing::warn!("{}, using the default API", e);
+                    packs::client::EtternaOnlineClient::new(Default::default())
+                })?;
+            app.manage(client);
+
+            app.manage(packs::catalog::Catalog::load());
+            packs::catalog::refresh_in_background(app.handle().clone());
+            Ok(())
+        })
+        .manage(packs::jobs::DownloadJobs::default())
+        .manage(packs::queue::DownloadQueue::default())
         .invoke_handler(tauri::generate_handler![
             greet,
             packs::api::fetch_packs,
+            packs::api::fetch_pack_details,
+            packs::catalog::get_catalog_status,
+            packs::catalog::sync_catalog,
+            packs::catalog::search_catalog,
             packs::api::get_sort_options,
             packs::download::download_pack,
+            packs::jobs::cancel_download,
+            packs::queue::get_download_queue,
+            packs::queue::pause_download,
+            packs::queue::resume_download,
+            packs::queue::move_download,
+            packs::uninstall::uninstall_pack,
             settings::get_settings,
-            settings::set_settings
+            settings::set_settings,
+            library::list_library,
+            library::get_library_pack,
+            library::remove_library_pack,
+            logging::export_logs
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
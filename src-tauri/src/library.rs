@@ -0,0 +1,243 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::error::{AppError, ErrorKind};
+
+/// Serializes read-modify-write cycles on library.json between concurrent downloads
+static LIBRARY_LOCK: Mutex<()> = Mutex::new(());
+
+/// A generated .osu file and its MD5 hash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryOsuFile {
+    pub path: String,
+    pub hash: String,
+}
+
+/// A pack installed by rOtterna and every file it produced
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryPack {
+    pub id: u64,
+    pub name: String,
+    /// Downloaded archive
+    pub archive_path: String,
+    /// SHA-256 of the downloaded archive, tells whether a re-download changed
+    #[serde(default)]
+    pub archive_sha256: Option<String>,
+    /// Directory the archive was extracted to
+    pub extract_path: String,
+    /// Directory the song folders were installed to
+    pub install_path: String,
+    pub song_folders: Vec<String>,
+    /// Files copied into the song folders, besides the generated .osu files
+    #[serde(default)]
+    pub song_files: Vec<String>,
+    pub osu_files: Vec<LibraryOsuFile>,
+    /// Name of the osu! collection created for the pack, if any
+    #[serde(default)]
+    pub collection_name: Option<String>,
+    /// Unix timestamps in seconds
+    pub installed_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Library {
+    pub packs: Vec<LibraryPack>,
+}
+
+impl Library {
+    fn get_library_path() -> Result<PathBuf, AppError> {
+        Ok(crate::paths::config_dir()?.join("library.json"))
+    }
+
+    pub fn load() -> Result<Library, AppError> {
+        Self::load_from(&Self::get_library_path()?)
+    }
+
+    fn load_from(library_path: &Path) -> Result<Library, AppError> {
+        if !library_path.exists() {
+            return Ok(Library::default());
+        }
+
+        let content = fs::read_to_string(library_path)
+            .map_err(|e| AppError::io("Error reading library file", e))?;
+
+        let library: Library = serde_json::from_str(&content).map_err(|e| {
+            AppError::new(ErrorKind::Io, format!("Error parsing library file: {}", e))
+                .with_details(library_path.display().to_string())
+        })?;
+
+        Ok(library)
+    }
+
+    pub fn save(&self) -> Result<(), AppError> {
+        self.save_to(&Self::get_library_path()?)
+    }
+
+    fn save_to(&self, library_path: &Path) -> Result<(), AppError> {
+        let content = serde_json::to_string_pretty(self).map_err(|e| {
+            AppError::new(ErrorKind::Io, format!("Error serializing library: {}", e))
+        })?;
+
+        // Write through a temporary file so a crash never leaves a truncated library
+        let tmp_path = library_path.with_extension("json.tmp");
+        fs::write(&tmp_path, content).map_err(|e| AppError::io("Error writing library file", e))?;
+        fs::rename(&tmp_path, library_path)
+            .map_err(|e| AppError::io("Error writing library file", e))?;
+
+        Ok(())
+    }
+
+    /// Loads the library, applies `f` and saves the result, holding the library lock
+    pub fn update<T>(f: impl FnOnce(&mut Library) -> T) -> Result<T, AppError> {
+        Self::update_at(&Self::get_library_path()?, f)
+    }
+
+    fn update_at<T>(library_path: &Path, f: impl FnOnce(&mut Library) -> T) -> Result<T, AppError> {
+        let _guard = LIBRARY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let mut library = Self::load_from(library_path)?;
+        let result = f(&mut library);
+        library.save_to(library_path)?;
+
+        Ok(result)
+    }
+
+    pub fn get(&self, pack_id: u64) -> Option<&LibraryPack> {
+        self.packs.iter().find(|pack| pack.id == pack_id)
+    }
+
+    /// Adds a pack, replacing the previous entry of a re-installed pack
+    /// The original install time is kept on re-install
+    pub fn upsert(&mut self, mut pack: LibraryPack) {
+        match self
+            .packs
+            .iter_mut()
+            .find(|existing| existing.id == pack.id)
+        {
+            Some(existing) => {
+                pack.installed_at = existing.installed_at;
+                *existing = pack;
+            }
+            None => self.packs.push(pack),
+        }
+    }
+
+    pub fn remove(&mut self, pack_id: u64) -> Option<LibraryPack> {
+        let index = self.packs.iter().position(|pack| pack.id == pack_id)?;
+        Some(self.packs.remove(index))
+    }
+}
+
+/// Current time as a Unix timestamp in seconds
+pub fn now_timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[tauri::command]
+pub fn list_library() -> Result<Vec<LibraryPack>, AppError> {
+    Ok(Library::load()?.packs)
+}
+
+#[tauri::command]
+pub fn get_library_pack(pack_id: u64) -> Result<Option<LibraryPack>, AppError> {
+    Ok(Library::load()?.get(pack_id).cloned())
+}
+
+/// Removes a pack from the library without touching its files
+#[tauri::command]
+#[tracing::instrument]
+pub fn remove_library_pack(pack_id: u64) -> Result<Option<LibraryPack>, AppError> {
+    Library::update(|library| library.remove(pack_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    fn pack(id: u64, name: &str, installed_at: u64) -> LibraryPack {
+        LibraryPack {
+            id,
+            name: name.to_string(),
+            archive_path: format!("downloads/{}.zip", name),
+            archive_sha256: Some("abc".to_string()),
+            extract_path: format!("downloads/{}", name),
+            install_path: "Songs".to_string(),
+            song_folders: vec![format!("Songs/{}", name)],
+            song_files: vec![format!("Songs/{}/audio.ogg", name)],
+            osu_files: vec![LibraryOsuFile {
+                path: format!("Songs/{}/Hard.osu", name),
+                hash: "d41d8cd98f00b204e9800998ecf8427e".to_string(),
+            }],
+            collection_name: None,
+            installed_at,
+            updated_at: installed_at,
+        }
+    }
+
+    #[test]
+    fn library_survives_a_save_and_load() {
+        let dir = TempDir::new("library");
+        let path = dir.join("library.json");
+        assert!(Library::load_from(&path).unwrap().packs.is_empty());
+
+        let library = Library {
+            packs: vec![pack(1, "First", 10), pack(2, "Second", 20)],
+        };
+        library.save_to(&path).unwrap();
+
+        let loaded = Library::load_from(&path).unwrap();
+        assert_eq!(loaded.packs.len(), 2);
+        let second = loaded.get(2).unwrap();
+        assert_eq!(second.name, "Second");
+        assert_eq!(second.archive_sha256.as_deref(), Some("abc"));
+        assert_eq!(second.osu_files[0].path, "Songs/Second/Hard.osu");
+        assert!(!path.with_extension("json.tmp").exists());
+
+        std::fs::write(&path, "not json").unwrap();
+        assert_eq!(Library::load_from(&path).unwrap_err().kind, ErrorKind::Io);
+    }
+
+    #[test]
+    fn reinstalls_replace_the_entry_and_keep_the_install_time() {
+        let mut library = Library::default();
+        library.upsert(pack(1, "Pack", 10));
+        library.upsert(pack(2, "Other", 15));
+
+        let mut reinstalled = pack(1, "Pack v2", 30);
+        reinstalled.updated_at = 30;
+        library.upsert(reinstalled);
+
+        assert_eq!(library.packs.len(), 2);
+        let pack = library.get(1).unwrap();
+        assert_eq!(pack.name, "Pack v2");
+        assert_eq!((pack.installed_at, pack.updated_at), (10, 30));
+    }
+
+    #[test]
+    fn removing_a_library_pack_keeps_the_others() {
+        let dir = TempDir::new("library-remove");
+        let path = dir.join("library.json");
+        Library::update_at(&path, |library| {
+            library.upsert(pack(1, "Kept", 10));
+            library.upsert(pack(2, "Removed", 10));
+        })
+        .unwrap();
+
+        let removed = Library::update_at(&path, |library| library.remove(2)).unwrap();
+        assert_eq!(removed.map(|pack| pack.name), Some("Removed".to_string()));
+        assert!(Library::update_at(&path, |library| library.remove(2))
+            .unwrap()
+            .is_none());
+
+        let library = Library::load_from(&path).unwrap();
+        assert!(library.get(1).is_some());
+        assert!(library.get(2).is_none());
+    }
+}
@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tauri::Manager;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+use crate::error::{AppError, ErrorKind};
+
+/// Log files are named `rotterna.<date>.log`, one per day
+const LOG_FILE_PREFIX: &str = "rotterna";
+const LOG_FILE_SUFFIX: &str = "log";
+/// Days of logs kept on disk
+const MAX_LOG_FILES: usize = 7;
+/// Days of logs included in an export
+const EXPORTED_LOG_FILES: usize = 3;
+
+/// Verbosity of the backend logs, set in the settings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    fn as_str(&self) -> &'static str {
+        match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        }
+    }
+
+    /// Applies the level to this crate, dependencies only log warnings
+    fn filter(&self) -> EnvFilter {
+        let crate_name = module_path!().split("::").next().unwrap_or_default();
+        EnvFilter::new(format!("warn,{}={}", crate_name, self.as_str()))
+    }
+}
+
+struct Logger {
+    filter: reload::Handle<EnvFilter, Registry>,
+    /// Flushes the file writer when dropped, kept for the lifetime of the app
+    _guard: tracing_appender::non_blocking::WorkerGuard,
+}
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// Logs to stdout and to a daily rotating file in `log_dir`
+pub fn init(log_dir: &Path, level: LogLevel) -> Result<(), AppError> {
+    let appender = tracing_appender::rolling::Builder::new()
+        .rotation(tracing_appender::rolling::Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(log_dir)
+        .map_err(|e| {
+            AppError::new(ErrorKind::Io, format!("Error creating log file: {}", e))
+                .with_details(log_dir.display().to_string())
+        })?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+
+    let (filter, handle) = reload::Layer::new(level.filter());
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(writer),
+        )
+        .try_init()
+        .map_err(|e| AppError::new(ErrorKind::Io, format!("Error initialising logging: {}", e)))?;
+
+    let _ = LOGGER.set(Logger {
+        filter: handle,
+        _guard: guard,
+    });
+    tracing::info!(log_dir = %log_dir.display(), level = level.as_str(), "Logging initialised");
+
+    Ok(())
+}
+
+/// Changes the verbosity of a running logger
+pub fn set_level(level: LogLevel) {
+    if let Some(logger) = LOGGER.get() {
+        if let Err(e) = logger.filter.reload(level.filter()) {
+            tracing::warn!("Error changing log level: {}", e);
+        }
+    }
+}
+
+/// Directory holding the log files
+pub fn log_dir(app: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+    let data_dir = app.path().app_data_dir().map_err(|e| {
+        AppError::new(
+            ErrorKind::Io,
+            format!("Error getting app data directory: {}", e),
+        )
+    })?;
+    Ok(data_dir.join("logs"))
+}
+
+/// Log files of the last days, oldest first
+fn recent_log_files(log_dir: &Path, count: usize) -> Result<Vec<PathBuf>, AppError> {
+    let entries =
+        std::fs::read_dir(log_dir).map_err(|e| AppError::io("Error reading log directory", e))?;
+
+    // Dates in the file names sort chronologically
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| {
+                    name.starts_with(LOG_FILE_PREFIX) && name.ends_with(LOG_FILE_SUFFIX)
+                })
+        })
+        .collect();
+    files.sort();
+
+    let skip = files.len().saturating_sub(count);
+    Ok(files.split_off(skip))
+}
+
+/// Writes the logs of the last days to `destination`, for bug reports
+/// Returns the path that was written
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+pub fn export_logs(app: tauri::AppHandle, destination: String) -> Result<String, AppError> {
+    let files = recent_log_files(&log_dir(&app)?, EXPORTED_LOG_FILES)?;
+
+    let mut output = std::fs::File::create(&destination)
+        .map_err(|e| AppError::io("Error creating log export", e).with_details(&destination))?;
+    for file in &files {
+        let mut input = std::fs::File::open(file)
+            .map_err(|e| AppError::io(format!("Error reading {}", file.display()), e))?;
+        std::io::copy(&mut input, &mut output)
+            .map_err(|e| AppError::io("Error writing log export", e).with_details(&destination))?;
+    }
+
+    tracing::info!("Exported {} log files", files.len());
+    Ok(destination)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    #[test]
+    fn recent_log_files_are_the_latest_days_in_order() {
+        let dir = TempDir::new("logs");
+        for name in [
+            "rotterna.2026-10-14.log",
+            "rotterna.2026-10-16.log",
+            "rotterna.2026-10-15.log",
+            "rotterna.2026-10-13.log",
+            "other.txt",
+        ] {
+            std::fs::write(dir.join(name), name).unwrap();
+        }
+
+        let files = recent_log_files(&dir, 3).unwrap();
+        let names: Vec<_> = files
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "rotterna.2026-10-14.log",
+                "rotterna.2026-10-15.log",
+                "rotterna.2026-10-16.log"
+            ]
+        );
+    }
+}
@@ -0,0 +1,310 @@
+use std::path::{Path, PathBuf};
+
+use crate::error::{AppError, ErrorKind};
+
+/// collection.db version written when creating a new file
+const DEFAULT_VERSION: i32 = 20150203;
+
+/// An osu! collection: a name and the MD5 hashes of its beatmaps
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collection {
+    pub name: String,
+    pub beatmap_hashes: Vec<String>,
+}
+
+/// Contents of osu!'s collection.db
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollectionDb {
+    pub version: i32,
+    pub collections: Vec<Collection>,
+}
+
+impl Default for CollectionDb {
+    fn default() -> Self {
+        CollectionDb {
+            version: DEFAULT_VERSION,
+            collections: Vec::new(),
+        }
+    }
+}
+
+impl CollectionDb {
+    /// Parses the binary collection.db format
+    pub fn read(bytes: &[u8]) -> Result<CollectionDb, String> {
+        let mut reader = Reader { bytes, pos: 0 };
+
+        let version = reader.read_i32()?;
+        let count = reader.read_i32()?;
+        if count < 0 {
+            return Err(format!("Invalid collection count: {}", count));
+        }
+
+        let mut collections = Vec::new();
+        for _ in 0..count {
+            let name = reader.read_string()?;
+            let beatmap_count = reader.read_i32()?;
+            if beatmap_count < 0 {
+                return Err(format!(
+                    "Invalid beatmap count in {}: {}",
+                    name, beatmap_count
+                ));
+            }
+
+            let mut beatmap_hashes = Vec::new();
+            for _ in 0..beatmap_count {
+                beatmap_hashes.push(reader.read_string()?);
+            }
+
+            collections.push(Collection {
+                name,
+                beatmap_hashes,
+            });
+        }
+
+        Ok(CollectionDb {
+            version,
+            collections,
+        })
+    }
+
+    /// Serializes to the binary collection.db format
+    pub fn write(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bytes.extend_from_slice(&(self.collections.len() as i32).to_le_bytes());
+
+        for collection in &self.collections {
+            write_string(&mut bytes, &collection.name);
+            bytes.extend_from_slice(&(collection.beatmap_hashes.len() as i32).to_le_bytes());
+            for hash in &collection.beatmap_hashes {
+                write_string(&mut bytes, hash);
+            }
+        }
+
+        bytes
+    }
+
+    /// Loads a collection.db file, or an empty database if it does not exist yet
+    pub fn load(path: &Path) -> Result<CollectionDb, AppError> {
+        if !path.exists() {
+            return Ok(CollectionDb::default());
+        }
+
+        let bytes =
+            std::fs::read(path).map_err(|e| AppError::io("Error reading collection.db", e))?;
+        CollectionDb::read(&bytes)
+            .map_err(|e| AppError::new(ErrorKind::Io, e).with_details(path.display().to_string()))
+    }
+
+    /// Writes the database atomically, keeping the previous file as collection.db.bak
+    pub fn save(&self, path: &Path) -> Result<(), AppError> {
+        let tmp_path = path.with_extension("db.tmp");
+        std::fs::write(&tmp_path, self.write())
+            .map_err(|e| AppError::io("Error writing collection.db", e))?;
+
+        if path.exists() {
+            std::fs::copy(path, path.with_extension("db.bak"))
+                .map_err(|e| AppError::io("Error backing up collection.db", e))?;
+        }
+
+        std::fs::rename(&tmp_path, path)
+            .map_err(|e| AppError::io("Error replacing collection.db", e))?;
+
+        Ok(())
+    }
+
+    /// Adds a collection, replacing any existing collection with the same name
+    pub fn upsert(&mut self, collection: Collection) {
+        match self
+            .collections
+            .iter_mut()
+            .find(|existing| existing.name == collection.name)
+        {
+            Some(existing) => *existing = collection,
+            None => self.collections.push(collection),
+        }
+    }
+
+    /// Removes the collection with the given name, returns whether it existed
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.collections.len();
+        self.collections
+            .retain(|collection| collection.name != name);
+        self.collections.len() != before
+    }
+}
+
+/// Finds collection.db from the configured Songs directory
+/// Returns None when the parent directory does not look like an osu! install
+pub fn collection_db_path(song_path: &str) -> Option<PathBuf> {
+    let osu_dir = Path::new(song_path).parent()?;
+    if osu_dir.join("osu!.db").exists() || osu_dir.join("collection.db").exists() {
+        Some(osu_dir.join("collection.db"))
+    } else {
+        None
+    }
+}
+
+/// MD5 hash of a beatmap file, as osu! stores it
+pub fn beatmap_hash(content: &[u8]) -> String {
+    format!("{:x}", md5::compute(content))
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn read_bytes(&mut self, len: usize) -> Result<&[u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| "Unexpected end of collection.db".to_string())?;
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, String> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_i32(&mut self) -> Result<i32, String> {
+        let bytes = self.read_bytes(4)?;
+        Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn read_uleb128(&mut self) -> Result<usize, String> {
+        let mut result = 0usize;
+        let mut shift = 0;
+        loop {
+            let byte = self.read_u8()?;
+            if shift >= usize::BITS {
+                return Err("Invalid string length in collection.db".to_string());
+            }
+            result |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+            shift += 7;
+        }
+    }
+
+    /// osu! strings: 0x00 for an empty string, or 0x0b, a ULEB128 length and UTF-8 bytes
+    fn read_string(&mut self) -> Result<String, String> {
+        match self.read_u8()? {
+            0x00 => Ok(String::new()),
+            0x0b => {
+                let len = self.read_uleb128()?;
+                let bytes = self.read_bytes(len)?;
+                String::from_utf8(bytes.to_vec())
+                    .map_err(|e| format!("Invalid string in collection.db: {}", e))
+            }
+            marker => Err(format!(
+                "Invalid string marker in collection.db: {:#x}",
+                marker
+            )),
+        }
+    }
+}
+
+fn write_string(bytes: &mut Vec<u8>, value: &str) {
+    if value.is_empty() {
+        bytes.push(0x00);
+        return;
+    }
+
+    bytes.push(0x0b);
+    let mut len = value.len();
+    loop {
+        let byte = (len & 0x7f) as u8;
+        len >>= 7;
+        if len == 0 {
+            bytes.push(byte);
+            break;
+        }
+        bytes.push(byte | 0x80);
+    }
+    bytes.extend_from_slice(value.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_a_collection_db_written_by_osu() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&20240820i32.to_le_bytes());
+        bytes.extend_from_slice(&1i32.to_le_bytes());
+        bytes.extend_from_slice(&[0x0b, 4]);
+        bytes.extend_from_slice(b"Jack");
+        bytes.extend_from_slice(&2i32.to_le_bytes());
+        bytes.extend_from_slice(&[0x0b, 32]);
+        bytes.extend_from_slice(b"0123456789abcdef0123456789abcdef");
+        bytes.push(0x00);
+
+        let db = CollectionDb::read(&bytes).unwrap();
+        assert_eq!(db.version, 20240820);
+        assert_eq!(db.collections.len(), 1);
+        assert_eq!(db.collections[0].name, "Jack");
+        assert_eq!(
+            db.collections[0].beatmap_hashes,
+            vec![
+                "0123456789abcdef0123456789abcdef".to_string(),
+                String::new()
+            ]
+        );
+
+        assert_eq!(db.write(), bytes);
+    }
+
+    #[test]
+    fn round_trips_long_and_unicode_names() {
+        let mut db = CollectionDb::default();
+        db.upsert(Collection {
+            name: "ダンエボ".repeat(20),
+            beatmap_hashes: vec![beatmap_hash(b"a"), beatmap_hash(b"b")],
+        });
+        db.upsert(Collection {
+            name: String::new(),
+            beatmap_hashes: Vec::new(),
+        });
+
+        let bytes = db.write();
+        assert_eq!(CollectionDb::read(&bytes).unwrap(), db);
+    }
+
+    #[test]
+    fn upsert_replaces_collection_with_same_name() {
+        let mut db = CollectionDb::default();
+        db.upsert(Collection {
+            name: "Pack".to_string(),
+            beatmap_hashes: vec![beatmap_hash(b"old")],
+        });
+        db.upsert(Collection {
+            name: "Pack".to_string(),
+            beatmap_hashes: vec![beatmap_hash(b"new")],
+        });
+
+        assert_eq!(db.collections.len(), 1);
+        assert_eq!(db.collections[0].beatmap_hashes, vec![beatmap_hash(b"new")]);
+        assert!(db.remove("Pack"));
+        assert!(!db.remove("Pack"));
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        let mut db = CollectionDb::default();
+        db.upsert(Collection {
+            name: "Pack".to_string(),
+            beatmap_hashes: vec![beatmap_hash(b"a")],
+        });
+        let bytes = db.write();
+
+        assert!(CollectionDb::read(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use super::tags::{parse_tags, sm_escape};
+
+/// Rows per measure in the generated .sm notes (48 rows per beat)
+const ROWS_PER_MEASURE: u32 = 192;
+
+/// Rows advanced by one DWI step outside of any bracket (an 8th note)
+const EIGHTH_ROWS: u32 = 24;
+
+/// Converts a .dwi file into an .sm document
+/// Returns None when the file has no playable single/double charts
+pub(super) fn to_sm_document(content: &str) -> Option<String> {
+    let tags = parse_tags(content);
+    let get = |key: &str| {
+        tags.iter()
+            .find(|(k, v)| k == key && !v.is_empty())
+            .map(|(_, v)| v.as_str())
+    };
+
+    let mut sm = String::new();
+
+    if let Some(title) = get("TITLE") {
+        let _ = writeln!(sm, "#TITLE:{};", sm_escape(title));
+    }
+    if let Some(artist) = get("ARTIST") {
+        let _ = writeln!(sm, "#ARTIST:{};", sm_escape(artist));
+    }
+    if let Some(file) = get("FILE") {
+        let _ = writeln!(sm, "#MUSIC:{};", sm_escape(file));
+    }
+    if let Some(start) = get("SAMPLESTART").and_then(parse_time) {
+        let _ = writeln!(sm, "#SAMPLESTART:{:.3};", start);
+    }
+    if let Some(length) = get("SAMPLELENGTH").and_then(parse_time) {
+        let _ = writeln!(sm, "#SAMPLELENGTH:{:.3};", length);
+    }
+
+    // GAP is the time in milliseconds before beat 0
+    let gap_ms: f64 = get("GAP")
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(0.0);
+    let _ = writeln!(sm, "#OFFSET:{:.3};", -gap_ms / 1000.0);
+
+    // CHANGEBPM and FREEZE positions are expressed in 16th notes
+    let bpm: f64 = get("BPM").and_then(|v| v.trim().parse().ok())?;
+    let mut bpms = vec![format!("0.000={:.3}", bpm)];
+    for (position, value) in parse_pairs(get("CHANGEBPM").unwrap_or("")) {
+        bpms.push(format!("{:.3}={:.3}", position / 4.0, value));
+    }
+    let _ = writeln!(sm, "#BPMS:{};", bpms.join(","));
+
+    let stops: Vec<String> = parse_pairs(get("FREEZE").unwrap_or(""))
+        .into_iter()
+        .map(|(position, ms)| format!("{:.3}={:.3}", position / 4.0, ms / 1000.0))
+        .collect();
+    let _ = writeln!(sm, "#STOPS:{};", stops.join(","));
+
+    let mut chart_count = 0;
+    for (key, value) in &tags {
+        let (stepstype, pads) = match key.as_str() {
+            "SINGLE" => ("dance-single", 1),
+            "DOUBLE" => ("dance-double", 2),
+            _ => continue,
+        };
+
+        // #SINGLE:difficulty:meter:notes; / #DOUBLE:difficulty:meter:left:right;
+        let parts: Vec<&str> = value.split(':').map(str::trim).collect();
+        if parts.len() < 2 + pads {
+            continue;
+        }
+
+        let mut rows = BTreeMap::new();
+        for (pad, notes) in parts[2..2 + pads].iter().enumerate() {
+            parse_steps(notes, pad * 4, pads * 4, &mut rows);
+        }
+
+        sm.push_str("#NOTES:\n");
+        let _ = writeln!(sm, "     {}:", stepstype);
+        sm.push_str("     :\n");
+        let _ = writeln!(sm, "     {}:", difficulty_name(parts[0]));
+        let _ = writeln!(sm, "     {}:", parts[1].parse::<u32>().unwrap_or(1));
+        sm.push_str("     0,0,0,0,0:\n");
+        sm.push_str(&measures_to_sm(&rows, pads * 4));
+        sm.push_str(";\n");
+
+        chart_count += 1;
+    }
+
+    if chart_count == 0 {
+        return None;
+    }
+
+    Some(sm)
+}
+
+/// Maps DWI difficulty names to their StepMania equivalents
+fn difficulty_name(dwi: &str) -> &'static str {
+    match dwi.to_uppercase().as_str() {
+        "BEGINNER" => "Beginner",
+        "BASIC" => "Easy",
+        "ANOTHER" => "Medium",
+        "MANIAC" => "Hard",
+        "SMANIAC" => "Challenge",
+        _ => "Edit",
+    }
+}
+
+/// Parses a time given either in seconds or as `MM:SS.ss`
+fn parse_time(value: &str) -> Option<f64> {
+    let mut seconds = 0.0;
+    for part in value.trim().split(':') {
+        seconds = seconds * 60.0 + part.trim().parse::<f64>().ok()?;
+    }
+    Some(seconds)
+}
+
+/// Parses comma-separated `position=value` pairs
+fn parse_pairs(value: &str) -> Vec<(f64, f64)> {
+    value
+        .split(',')
+        .filter_map(|pair| {
+            let (position, value) = pair.split_once('=')?;
+            Some((position.trim().parse().ok()?, value.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+/// Returns which of the four panels (left, down, up, right) a DWI step presses
+fn step_columns(step: char) -> &'static [usize] {
+    match step.to_ascii_uppercase() {
+        '1' => &[0, 1],
+        '2' => &[1],
+        '3' => &[1, 3],
+        '4' => &[0],
+        '6' => &[3],
+        '7' => &[0, 2],
+        '8' => &[2],
+        '9' => &[2, 3],
+        'A' => &[1, 2],
+        'B' => &[0, 3],
+        _ => &[],
+    }
+}
+
+/// Parses the DWI step string of one pad into .sm note characters keyed by row
+fn parse_steps(
+    notes: &str,
+    column_offset: usize,
+    column_count: usize,
+    rows: &mut BTreeMap<u32, Vec<char>>,
+) {
+    let chars: Vec<char> = notes.chars().filter(|c| !c.is_whitespace()).collect();
+    let mut held = [false; 4];
+    let mut row = 0u32;
+    let mut increment = EIGHTH_ROWS;
+    let mut in_chord = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+
+        match c {
+            '(' => increment = 12,
+            '[' => increment = 8,
+            '{' => increment = 3,
+            '`' => increment = 1,
+            ')' | ']' | '}' | '\'' => increment = EIGHTH_ROWS,
+            '<' => in_chord = true,
+            '>' => {
+                in_chord = false;
+                row += increment;
+            }
+            '!' => {
+                // Stray hold marker without a preceding step, skip its arrows
+                i += 1;
+            }
+            step => {
+                let mut hold_columns: &[usize] = &[];
+                if chars.get(i) == Some(&'!') {
+                    hold_columns = chars.get(i + 1).map_or(&[], |h| step_columns(*h));
+                    i += 2;
+                }
+
+                let line = rows.entry(row).or_insert_with(|| vec!['0'; column_count]);
+                for &column in step_columns(step) {
+                    let note = if held[column] {
+                        // The next step on a held panel releases the hold
+                        held[column] = false;
+                        '3'
+                    } else if hold_columns.contains(&column) {
+                        held[column] = true;
+                        '2'
+                    } else {
+                        '1'
+                    };
+                    line[column_offset + column] = note;
+                }
+
+                if !in_chord {
+                    row += increment;
+                }
+            }
+        }
+    }
+}
+
+/// Lays out rows of notes as .sm measures, using the coarsest subdivision that fits
+fn measures_to_sm(rows: &BTreeMap<u32, Vec<char>>, column_count: usize) -> String {
+    const SUBDIVISIONS: &[u32] = &[4, 8, 12, 16, 24, 32, 48, 64, 96, 192];
+
+    let measure_count = rows
+        .keys()
+        .next_back()
+        .map_or(1, |last| last / ROWS_PER_MEASURE + 1);
+    let empty_line = "0".repeat(column_count);
+    let mut output = String::new();
+
+    for measure in 0..measure_count {
+        let start = measure * ROWS_PER_MEASURE;
+        let measure_rows: Vec<u32> = rows
+            .range(start..start + ROWS_PER_MEASURE)
+            .map(|(row, _)| row - start)
+            .collect();
+
+        let lines = SUBDIVISIONS
+            .iter()
+            .copied()
+            .find(|lines| {
+                let step = ROWS_PER_MEASURE / lines;
+                measure_rows.iter().all(|row| row % step == 0)
+            })
+            .unwrap_or(ROWS_PER_MEASURE);
+        let step = ROWS_PER_MEASURE / lines;
+
+        if measure > 0 {
+            output.push_str(",\n");
+        }
+        for line in 0..lines {
+            match rows.get(&(start + line * step)) {
+                Some(notes) => output.extend(notes.iter()),
+                None => output.push_str(&empty_line),
+            }
+            output.push('\n');
+        }
+    }
+
+    output
+}
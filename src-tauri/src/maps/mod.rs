@@ -1,40 +1,467 @@
+pub mod collection;
+mod dwi;
+pub mod rates;
+mod ssc;
+mod tags;
+
+use std::path::Path;
+
 use rhythm_open_exchange::codec::formats::osu::OsuEncoder;
+use rhythm_open_exchange::codec::formats::sm::parser as sm_parser;
+use rhythm_open_exchange::codec::formats::sm::types::{SmChart, SmFile};
 use rhythm_open_exchange::codec::formats::sm::SmDecoder;
-use rhythm_open_exchange::codec::{Decoder, Encoder};
+use rhythm_open_exchange::codec::Encoder;
+
+use crate::settings::Settings;
+
+/// StepMania steps types that can be played in osu!mania
+const SUPPORTED_STEPSTYPES: &[&str] = &["dance-single", "dance-double"];
+
+/// A generated .osu file
+#[derive(Debug, Clone)]
+pub struct OsuFile {
+    /// Difficulty name written to the file (unique within a song)
+    pub difficulty_name: String,
+    pub content: Vec<u8>,
+    /// Audio file of the original song
+    pub audio_file: String,
+    /// Playback rate of the chart, 1.0 for the original
+    pub rate: f64,
+}
+
+/// Why a simfile could not be converted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConversionError {
+    /// The simfile could not be parsed or has no supported chart
+    Decode(String),
+    /// A chart could not be written as .osu
+    Encode(String),
+}
+
+impl std::fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConversionError::Decode(message) | ConversionError::Encode(message) => {
+                f.write_str(message)
+            }
+        }
+    }
+}
+
+/// Simfile formats that can be converted to .osu
+/// Variants are ordered by preference when a song ships several formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SimfileFormat {
+    Ssc,
+    Sm,
+    Dwi,
+}
+
+impl SimfileFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "ssc" => Some(SimfileFormat::Ssc),
+            "sm" => Some(SimfileFormat::Sm),
+            "dwi" => Some(SimfileFormat::Dwi),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SimfileFormat::Ssc => "ssc",
+            SimfileFormat::Sm => "sm",
+            SimfileFormat::Dwi => "dwi",
+        }
+    }
+}
+
+/// Reads the #TITLE of a .sm, .ssc or .dwi file
+pub fn simfile_title(content: &str) -> Option<String> {
+    tags::parse_tags(content)
+        .into_iter()
+        .find(|(key, value)| key == "TITLE" && !value.is_empty())
+        .map(|(_, value)| value)
+}
+
+/// Converts a simfile buffer of the given format to .osu format
+/// Returns one .osu file per chart and per configured rate
+pub fn from_simfile_to_osu(
+    format: SimfileFormat,
+    file_buff: Vec<u8>,
+    settings: &Settings,
+) -> Result<Vec<OsuFile>, ConversionError> {
+    match format {
+        SimfileFormat::Ssc => from_ssc_to_osu(file_buff, settings),
+        SimfileFormat::Sm => from_sm_to_osu(file_buff, settings),
+        SimfileFormat::Dwi => from_dwi_to_osu(file_buff, settings),
+    }
+}
 
 /// Converts a .sm file buffer to .osu format
-/// Returns a Vec of (difficulty_name, osu_content_bytes) tuples
-pub fn from_sm_to_osu(file_buff: Vec<u8>) -> Result<Vec<(String, Vec<u8>)>, String> {
-    println!("[from_sm_to_osu] Converting .sm file to .osu format...");
-    println!("[from_sm_to_osu] File size: {} bytes", file_buff.len());
-
-    // Decode SM file using rhythm-open-exchange
-    let chart =
-        SmDecoder::decode(&file_buff).map_err(|e| format!("Error decoding SM file: {}", e))?;
-
-    println!(
-        "[from_sm_to_osu] Decoded .sm file: {} - {} ({}K, {} notes)",
-        chart.metadata.title,
-        chart.metadata.artist,
-        chart.key_count,
-        chart.notes.len()
+/// Returns one .osu file per chart and per configured rate
+pub fn from_sm_to_osu(
+    file_buff: Vec<u8>,
+    settings: &Settings,
+) -> Result<Vec<OsuFile>, ConversionError> {
+    tracing::debug!("Converting .sm file to .osu format...");
+    tracing::debug!("File size: {} bytes", file_buff.len());
+
+    // Parse the SM file using rhythm-open-exchange
+    let sm = sm_parser::parse(&file_buff)
+        .map_err(|e| ConversionError::Decode(format!("Error decoding SM file: {}", e)))?;
+
+    let mut osu_files: Vec<OsuFile> = Vec::new();
+    encode_charts(&sm, settings, &mut osu_files).map_err(ConversionError::Encode)?;
+
+    if osu_files.is_empty() {
+        return Err(ConversionError::Decode(
+            "No supported charts found in SM file".to_string(),
+        ));
+    }
+
+    tracing::debug!("Generated {} .osu file(s)", osu_files.len());
+
+    Ok(osu_files)
+}
+
+/// Converts a .ssc file buffer to .osu format
+/// Charts with their own timing are converted with that timing
+pub fn from_ssc_to_osu(
+    file_buff: Vec<u8>,
+    settings: &Settings,
+) -> Result<Vec<OsuFile>, ConversionError> {
+    tracing::debug!("Converting .ssc file to .osu format...");
+    tracing::debug!("File size: {} bytes", file_buff.len());
+
+    let content = String::from_utf8_lossy(&file_buff);
+
+    let mut osu_files: Vec<OsuFile> = Vec::new();
+    for document in ssc::to_sm_documents(&content) {
+        let sm = sm_parser::parse(document.as_bytes())
+            .map_err(|e| ConversionError::Decode(format!("Error decoding SSC file: {}", e)))?;
+        encode_charts(&sm, settings, &mut osu_files).map_err(ConversionError::Encode)?;
+    }
+
+    if osu_files.is_empty() {
+        return Err(ConversionError::Decode(
+            "No supported charts found in SSC file".to_string(),
+        ));
+    }
+
+    tracing::debug!("Generated {} .osu file(s)", osu_files.len());
+
+    Ok(osu_files)
+}
+
+/// Converts a .dwi file buffer to .osu format
+pub fn from_dwi_to_osu(
+    file_buff: Vec<u8>,
+    settings: &Settings,
+) -> Result<Vec<OsuFile>, ConversionError> {
+    tracing::debug!("Converting .dwi file to .osu format...");
+    tracing::debug!("File size: {} bytes", file_buff.len());
+
+    let content = String::from_utf8_lossy(&file_buff);
+    let no_charts = || ConversionError::Decode("No supported charts found in DWI file".to_string());
+    let document = dwi::to_sm_document(&content).ok_or_else(no_charts)?;
+
+    let sm = sm_parser::parse(document.as_bytes())
+        .map_err(|e| ConversionError::Decode(format!("Error decoding DWI file: {}", e)))?;
+
+    let mut osu_files: Vec<OsuFile> = Vec::new();
+    encode_charts(&sm, settings, &mut osu_files).map_err(ConversionError::Encode)?;
+
+    if osu_files.is_empty() {
+        return Err(no_charts());
+    }
+
+    tracing::debug!("Generated {} .osu file(s)", osu_files.len());
+
+    Ok(osu_files)
+}
+
+/// Encodes every supported chart of a parsed simfile, along with its rated
+/// versions, and appends them to `osu_files`
+fn encode_charts(
+    sm: &SmFile,
+    settings: &Settings,
+    osu_files: &mut Vec<OsuFile>,
+) -> Result<(), String> {
+    tracing::debug!(
+        "Parsed simfile: {} - {} ({} charts)",
+        sm.metadata.title,
+        sm.metadata.artist,
+        sm.charts.len()
     );
 
-    // Encode to osu! format
-    let osu_data =
-        OsuEncoder::encode(&chart).map_err(|e| format!("Error encoding to osu!: {}", e))?;
+    for sm_chart in &sm.charts {
+        let stepstype = sm_chart.stepstype.trim().to_lowercase();
+        if !SUPPORTED_STEPSTYPES.contains(&stepstype.as_str()) {
+            tracing::debug!("Skipping unsupported chart type: {}", sm_chart.stepstype);
+            continue;
+        }
+
+        let mut chart = SmDecoder::from_chart(sm, sm_chart);
+
+        // Each chart needs its own difficulty name so osu! lists them separately
+        let base_name = difficulty_name(sm_chart);
+        let mut name = base_name.clone();
+        let mut suffix = 2;
+        while osu_files.iter().any(|file| file.difficulty_name == name) {
+            name = format!("{} ({})", base_name, suffix);
+            suffix += 1;
+        }
+        chart.metadata.difficulty_name = name.clone();
+
+        tracing::debug!(
+            "Encoding chart: {} ({}K, {} notes)",
+            name,
+            chart.key_count,
+            chart.notes.len()
+        );
+
+        // Encode to osu! format
+        let osu_data =
+            OsuEncoder::encode(&chart).map_err(|e| format!("Error encoding to osu!: {}", e))?;
+        let osu_data = apply_difficulty_settings(osu_data, settings)?;
+
+        osu_files.push(OsuFile {
+            difficulty_name: name,
+            content: osu_data,
+            audio_file: chart.metadata.audio_file.clone(),
+            rate: 1.0,
+        });
+
+        osu_files.extend(rates::from_chart_to_rated_osu(
+            &chart,
+            &settings.rates(),
+            settings,
+        )?);
+    }
 
-    // Use the chart's difficulty name or default to "Unknown"
-    let difficulty_name = if chart.metadata.difficulty_name.is_empty() {
+    Ok(())
+}
+
+/// Builds the osu! difficulty name for a StepMania chart
+fn difficulty_name(sm_chart: &SmChart) -> String {
+    let difficulty = sm_chart.difficulty.trim();
+    let description = sm_chart.description.trim();
+
+    // Edit charts are usually identified by their description
+    let mut name = if difficulty.eq_ignore_ascii_case("edit") && !description.is_empty() {
+        description.to_string()
+    } else if difficulty.is_empty() {
         "Unknown".to_string()
     } else {
-        chart.metadata.difficulty_name.clone()
+        difficulty.to_string()
     };
 
-    println!(
-        "[from_sm_to_osu] Generated 1 .osu file (difficulty: {})",
-        difficulty_name
-    );
+    if sm_chart.column_count != 4 {
+        name = format!("{} ({}K)", name, sm_chart.column_count);
+    }
+
+    name
+}
+
+/// Rewrites HPDrainRate and OverallDifficulty in the [Difficulty] section
+/// of an encoded .osu file with the values from the settings
+pub(crate) fn apply_difficulty_settings(
+    osu_data: Vec<u8>,
+    settings: &Settings,
+) -> Result<Vec<u8>, String> {
+    let content =
+        String::from_utf8(osu_data).map_err(|e| format!("Invalid UTF-8 in .osu output: {}", e))?;
+
+    let mut output = String::with_capacity(content.len());
+    let mut in_difficulty = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            in_difficulty = trimmed == "[Difficulty]";
+        }
+
+        if in_difficulty && trimmed.starts_with("HPDrainRate:") {
+            output.push_str(&format!("HPDrainRate:{}", settings.hp_drain_rate));
+        } else if in_difficulty && trimmed.starts_with("OverallDifficulty:") {
+            output.push_str(&format!(
+                "OverallDifficulty:{}",
+                settings.overall_difficulty
+            ));
+        } else {
+            output.push_str(line);
+        }
+        output.push('\n');
+    }
+
+    Ok(output.into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::SIMPLE_SM;
+    use rosu_map::section::difficulty::Difficulty;
+
+    #[test]
+    fn converted_files_use_hp_and_od_from_settings() {
+        let settings = Settings {
+            hp_drain_rate: 7.5,
+            overall_difficulty: 8.2,
+            ..Settings::default()
+        };
+
+        let files = from_sm_to_osu(SIMPLE_SM.as_bytes().to_vec(), &settings).unwrap();
+        assert!(!files.is_empty());
+
+        for file in files {
+            let difficulty = rosu_map::from_bytes::<Difficulty>(&file.content).unwrap();
+            assert_eq!(difficulty.hp_drain_rate, 7.5);
+            assert_eq!(difficulty.overall_difficulty, 8.2);
+            assert_eq!(difficulty.circle_size, 4.0);
+        }
+    }
+
+    #[test]
+    fn every_supported_chart_is_converted() {
+        let mut content = SIMPLE_SM.to_string();
+        content.push_str(
+            "#NOTES:
+     dance-single:
+     :
+     Challenge:
+     12:
+     0,0,0,0,0:
+1100
+0011
+;
+#NOTES:
+     dance-single:
+     Some Mapper:
+     Edit:
+     13:
+     0,0,0,0,0:
+1001
+0110
+;
+#NOTES:
+     dance-double:
+     :
+     Hard:
+     11:
+     0,0,0,0,0:
+10000001
+01000010
+;
+#NOTES:
+     pump-single:
+     :
+     Hard:
+     9:
+     0,0,0,0,0:
+10000
+01000
+;
+",
+        );
+
+        let files = from_sm_to_osu(content.into_bytes(), &Settings::default()).unwrap();
+        let names: Vec<&str> = files.iter().map(|f| f.difficulty_name.as_str()).collect();
+        assert_eq!(names, vec!["Hard", "Challenge", "Some Mapper", "Hard (8K)"]);
+
+        for file in &files {
+            let metadata =
+                rosu_map::from_bytes::<rosu_map::section::metadata::Metadata>(&file.content)
+                    .unwrap();
+            assert_eq!(metadata.version, file.difficulty_name);
+        }
+    }
+
+    #[test]
+    fn ssc_charts_use_their_own_timing() {
+        let ssc = "#VERSION:0.83;
+#TITLE:Test Song;
+#ARTIST:Test Artist;
+#MUSIC:audio.ogg;
+#OFFSET:0.000;
+#BPMS:0.000=120.000;
+#NOTEDATA:;
+#STEPSTYPE:dance-single;
+#DIFFICULTY:Hard;
+#METER:10;
+#NOTES:
+1000
+0100
+0010
+0001
+;
+#NOTEDATA:;
+#STEPSTYPE:dance-single;
+#DIFFICULTY:Challenge;
+#METER:12;
+#BPMS:0.000=240.000;
+#NOTES:
+1000
+0100
+0010
+0001
+;
+";
+
+        let files = from_ssc_to_osu(ssc.as_bytes().to_vec(), &Settings::default()).unwrap();
+        let names: Vec<&str> = files.iter().map(|f| f.difficulty_name.as_str()).collect();
+        assert_eq!(names, vec!["Hard", "Challenge"]);
+
+        // One measure of 4 quarter notes: 500ms apart at 120 BPM, 250ms at 240 BPM
+        let last_note = |osu_bytes: &[u8]| {
+            let hit_objects =
+                rosu_map::from_bytes::<rosu_map::section::hit_objects::HitObjects>(osu_bytes)
+                    .unwrap();
+            hit_objects.hit_objects.last().unwrap().start_time
+        };
+        assert_eq!(last_note(&files[0].content), 1500.0);
+        assert_eq!(last_note(&files[1].content), 750.0);
+    }
+
+    #[test]
+    fn dwi_steps_are_converted() {
+        let dwi = "#TITLE:Test Song;
+#ARTIST:Test Artist;
+#BPM:120.000;
+#GAP:0;
+#SINGLE:BASIC:3:4268;
+#SINGLE:MANIAC:9:<48>0B(2222)8!8008;
+#DOUBLE:ANOTHER:6:4000:0006;
+";
+
+        let files = from_dwi_to_osu(dwi.as_bytes().to_vec(), &Settings::default()).unwrap();
+        let names: Vec<&str> = files.iter().map(|f| f.difficulty_name.as_str()).collect();
+        assert_eq!(names, vec!["Easy", "Hard", "Medium (8K)"]);
+
+        let hit_objects = |osu_bytes: &[u8]| {
+            rosu_map::from_bytes::<rosu_map::section::hit_objects::HitObjects>(osu_bytes)
+                .unwrap()
+                .hit_objects
+        };
+
+        // 8th notes at 120 BPM are 250ms apart
+        let easy = hit_objects(&files[0].content);
+        let times: Vec<f64> = easy.iter().map(|h| h.start_time).collect();
+        assert_eq!(times, vec![0.0, 250.0, 500.0, 750.0]);
+
+        // Chord, rest, jump, 16th notes, then a hold on up released three 8ths later
+        let hard = hit_objects(&files[1].content);
+        assert_eq!(hard.len(), 2 + 2 + 4 + 1);
+        let hold = hard.last().unwrap();
+        assert_eq!(hold.start_time, 1250.0);
+        let rosu_map::section::hit_objects::HitObjectKind::Hold(ref hold_data) = hold.kind else {
+            panic!("expected a hold note");
+        };
+        assert_eq!(hold_data.duration, 750.0);
 
-    Ok(vec![(difficulty_name, osu_data)])
+        let double = hit_objects(&files[2].content);
+        assert_eq!(double.len(), 2);
+    }
 }
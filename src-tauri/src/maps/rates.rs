@@ -0,0 +1,192 @@
+use std::path::Path;
+
+use rhythm_open_exchange::codec::formats::osu::OsuEncoder;
+use rhythm_open_exchange::codec::Encoder;
+use rhythm_open_exchange::model::NoteType;
+use rhythm_open_exchange::RoxChart;
+
+use super::{apply_difficulty_settings, OsuFile};
+use crate::error::{AppError, ErrorKind};
+use crate::settings::Settings;
+
+/// Range of rates a single ffmpeg `atempo` filter accepts
+pub const RATE_RANGE: std::ops::RangeInclusive<f64> = 0.5..=100.0;
+/// Most rates generated per chart, each one renders its own audio
+pub const MAX_RATE_COUNT: usize = 50;
+
+/// Number of steps of `step` between `min` and `max` (inclusive)
+pub fn rate_count(min: f64, max: f64, step: f64) -> f64 {
+    ((max - min) / step + 1e-9).floor() + 1.0
+}
+
+/// Lists the rates between `min` and `max` (inclusive) every `step`, leaving out 1.0x
+/// At most `MAX_RATE_COUNT` rates are listed, starting from `min`
+pub fn rates_in_range(min: f64, max: f64, step: f64) -> Vec<f64> {
+    if step.is_nan() || step <= 0.0 || min <= 0.0 || max < min {
+        return Vec::new();
+    }
+
+    let count = (rate_count(min, max, step) as usize).min(MAX_RATE_COUNT) - 1;
+    (0..=count)
+        .map(|i| ((min + i as f64 * step) * 100.0).round() / 100.0)
+        .filter(|rate| (rate - 1.0).abs() > f64::EPSILON)
+        .collect()
+}
+
+/// Formats a rate the way osu! players write it (1.0x, 1.2x, 1.05x)
+pub fn format_rate(rate: f64) -> String {
+    let mut formatted = format!("{:.2}", rate);
+    if formatted.ends_with('0') {
+        formatted.pop();
+    }
+    format!("{}x", formatted)
+}
+
+/// Name of the audio file rendered for a rate, next to the original audio
+pub fn rated_audio_file_name(audio_file: &str, rate: f64) -> String {
+    let stem = Path::new(audio_file)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("audio");
+    format!("{} {}.mp3", stem, format_rate(rate))
+}
+
+/// Returns a copy of the chart played at the given rate
+/// Every timestamp is divided by the rate and every BPM multiplied by it
+pub fn rate_chart(chart: &RoxChart, rate: f64) -> RoxChart {
+    let scale = |time_us: i64| (time_us as f64 / rate).round() as i64;
+
+    let mut rated = chart.clone();
+
+    rated.metadata.difficulty_name =
+        format!("{} {}", chart.metadata.difficulty_name, format_rate(rate));
+    rated.metadata.audio_file = rated_audio_file_name(&chart.metadata.audio_file, rate);
+    rated.metadata.audio_offset_us = scale(chart.metadata.audio_offset_us);
+    rated.metadata.preview_time_us = scale(chart.metadata.preview_time_us);
+    rated.metadata.preview_duration_us = scale(chart.metadata.preview_duration_us);
+
+    for timing_point in &mut rated.timing_points {
+        timing_point.time_us = scale(timing_point.time_us);
+        if !timing_point.is_inherited {
+            timing_point.bpm = (f64::from(timing_point.bpm) * rate) as f32;
+        }
+    }
+
+    for note in &mut rated.notes {
+        note.time_us = scale(note.time_us);
+        match &mut note.note_type {
+            NoteType::Hold { duration_us } | NoteType::Burst { duration_us } => {
+                *duration_us = scale(*duration_us).max(1);
+            }
+            NoteType::Tap | NoteType::Mine => {}
+        }
+    }
+
+    rated
+}
+
+/// Encodes a decoded chart once per rate
+pub fn from_chart_to_rated_osu(
+    chart: &RoxChart,
+    rates: &[f64],
+    settings: &Settings,
+) -> Result<Vec<OsuFile>, String> {
+    rates
+        .iter()
+        .map(|&rate| {
+            let rated = rate_chart(chart, rate);
+
+            let osu_data = OsuEncoder::encode(&rated)
+                .map_err(|e| format!("Error encoding {} to osu!: {}", format_rate(rate), e))?;
+            let osu_data = apply_difficulty_settings(osu_data, settings)?;
+
+            Ok(OsuFile {
+                difficulty_name: rated.metadata.difficulty_name,
+                content: osu_data,
+                audio_file: chart.metadata.audio_file.clone(),
+                rate,
+            })
+        })
+        .collect()
+}
+
+/// Renders a time-stretched copy of an audio file with ffmpeg, keeping its pitch
+pub fn generate_rated_audio(
+    ffmpeg_path: &str,
+    input: &Path,
+    output: &Path,
+    rate: f64,
+) -> Result<(), AppError> {
+    tracing::debug!("Rendering {} at {}", input.display(), format_rate(rate));
+
+    let result = std::process::Command::new(ffmpeg_path)
+        .arg("-y")
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-i")
+        .arg(input)
+        .arg("-vn")
+        .arg("-filter:a")
+        .arg(format!("atempo={}", rate))
+        .arg(output)
+        .output()
+        .map_err(|e| {
+            AppError::new(
+                ErrorKind::Conversion,
+                format!("Error running ffmpeg ({}): {}", ffmpeg_path, e),
+            )
+            .with_details(ffmpeg_path)
+        })?;
+
+    if !result.status.success() {
+        return Err(AppError::new(
+            ErrorKind::Conversion,
+            format!(
+                "ffmpeg failed for {}: {}",
+                input.display(),
+                String::from_utf8_lossy(&result.stderr).trim()
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rhythm_open_exchange::{Note, TimingPoint};
+
+    #[test]
+    fn rates_cover_the_range_without_1x() {
+        assert_eq!(rates_in_range(0.9, 1.2, 0.1), vec![0.9, 1.1, 1.2]);
+        assert_eq!(rates_in_range(1.0, 1.0, 0.1), Vec::<f64>::new());
+        assert_eq!(rates_in_range(1.1, 1.0, 0.1), Vec::<f64>::new());
+        assert_eq!(rates_in_range(0.5, 2.0, 0.0001).len(), MAX_RATE_COUNT);
+        assert_eq!(format_rate(1.2), "1.2x");
+        assert_eq!(format_rate(1.05), "1.05x");
+    }
+
+    #[test]
+    fn rated_chart_is_time_scaled() {
+        let mut chart = RoxChart::new(4);
+        chart.metadata.difficulty_name = "Hard".to_string();
+        chart.metadata.audio_file = "song.ogg".to_string();
+        chart.metadata.preview_time_us = 12_000_000;
+        chart.timing_points.push(TimingPoint::bpm(0, 150.0));
+        chart.timing_points.push(TimingPoint::bpm(6_000_000, 200.0));
+        chart.notes.push(Note::tap(1_200_000, 0));
+        chart.notes.push(Note::hold(2_400_000, 600_000, 1));
+
+        let rated = rate_chart(&chart, 1.2);
+
+        assert_eq!(rated.metadata.difficulty_name, "Hard 1.2x");
+        assert_eq!(rated.metadata.audio_file, "song 1.2x.mp3");
+        assert_eq!(rated.metadata.preview_time_us, 10_000_000);
+        assert_eq!(rated.timing_points[1].time_us, 5_000_000);
+        assert_eq!(rated.timing_points[1].bpm, 240.0);
+        assert_eq!(rated.notes[0].time_us, 1_000_000);
+        assert_eq!(rated.notes[1].time_us, 2_000_000);
+        assert_eq!(rated.notes[1].duration_us(), 500_000);
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt::Write;
+
+use super::tags::{parse_tags, sm_escape, sm_escape_field};
+
+/// Song-level tags that are carried over to every generated .sm document
+const SONG_TAGS: &[&str] = &[
+    "TITLE",
+    "SUBTITLE",
+    "ARTIST",
+    "TITLETRANSLIT",
+    "ARTISTTRANSLIT",
+    "CREDIT",
+    "MUSIC",
+    "BANNER",
+    "BACKGROUND",
+    "SAMPLESTART",
+    "SAMPLELENGTH",
+];
+
+/// Timing tags that an .ssc chart may override
+const TIMING_TAGS: &[&str] = &["OFFSET", "BPMS", "STOPS"];
+
+#[derive(Debug, Default)]
+struct SscChart {
+    tags: Vec<(String, String)>,
+}
+
+impl SscChart {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(k, v)| k == key && !v.is_empty())
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Splits an .ssc file into one .sm document per chart
+/// Each document carries the chart's own timing when it overrides the song timing
+pub(super) fn to_sm_documents(content: &str) -> Vec<String> {
+    let mut song_tags: Vec<(String, String)> = Vec::new();
+    let mut charts: Vec<SscChart> = Vec::new();
+
+    for (key, value) in parse_tags(content) {
+        if key == "NOTEDATA" {
+            charts.push(SscChart::default());
+        } else if let Some(chart) = charts.last_mut() {
+            chart.tags.push((key, value));
+        } else {
+            song_tags.push((key, value));
+        }
+    }
+
+    let song_get = |key: &str| {
+        song_tags
+            .iter()
+            .find(|(k, v)| k == key && !v.is_empty())
+            .map(|(_, v)| v.as_str())
+    };
+
+    charts
+        .iter()
+        .filter_map(|chart| {
+            let notes = chart.get("NOTES")?;
+            let mut sm = String::new();
+
+            for tag in SONG_TAGS {
+                // Charts can have their own credit, which is the mapper
+                let value = if *tag == "CREDIT" {
+                    chart.get(tag).or_else(|| song_get(tag))
+                } else {
+                    song_get(tag)
+                };
+                if let Some(value) = value {
+                    let _ = writeln!(sm, "#{}:{};", tag, sm_escape(value));
+                }
+            }
+
+            for tag in TIMING_TAGS {
+                if let Some(value) = chart.get(tag).or_else(|| song_get(tag)) {
+                    let _ = writeln!(sm, "#{}:{};", tag, sm_escape(value));
+                }
+            }
+
+            let description = chart
+                .get("DESCRIPTION")
+                .or_else(|| chart.get("CHARTNAME"))
+                .unwrap_or("");
+
+            sm.push_str("#NOTES:\n");
+            let _ = writeln!(
+                sm,
+                "     {}:",
+                sm_escape_field(chart.get("STEPSTYPE").unwrap_or(""))
+            );
+            let _ = writeln!(sm, "     {}:", sm_escape_field(description));
+            let _ = writeln!(
+                sm,
+                "     {}:",
+                sm_escape_field(chart.get("DIFFICULTY").unwrap_or(""))
+            );
+            let _ = writeln!(
+                sm,
+                "     {}:",
+                sm_escape_field(chart.get("METER").unwrap_or("1"))
+            );
+            sm.push_str("     0,0,0,0,0:\n");
+            sm.push_str(notes);
+            sm.push_str("\n;\n");
+
+            Some(sm)
+        })
+        .collect()
+}
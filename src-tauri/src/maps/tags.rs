@@ -0,0 +1,76 @@
+/// Splits simfile content into `#KEY:value;` pairs, in file order
+/// Keys are uppercased, values are kept as-is (including inner colons and newlines)
+pub(super) fn parse_tags(content: &str) -> Vec<(String, String)> {
+    // Strip `//` comments before tokenizing
+    let content: String = content
+        .lines()
+        .map(|line| match line.find("//") {
+            Some(pos) => &line[..pos],
+            None => line,
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut tags = Vec::new();
+    let mut rest = content.as_str();
+
+    while let Some(start) = rest.find('#') {
+        rest = &rest[start + 1..];
+
+        let Some(colon) = rest.find(':') else {
+            break;
+        };
+        let key = rest[..colon].trim().to_uppercase();
+        rest = &rest[colon + 1..];
+
+        // A missing terminator ends the value at the next tag (or EOF), that tag is kept
+        let next_tag = rest.find("\n#").map(|pos| pos + 1);
+        let value_end = match (rest.find(';'), next_tag) {
+            (Some(semicolon), Some(tag)) if tag < semicolon => tag,
+            (Some(semicolon), _) => semicolon,
+            (None, tag) => tag.unwrap_or(rest.len()),
+        };
+        let value = rest[..value_end].trim().to_string();
+        rest = match rest[value_end..].strip_prefix(';') {
+            Some(after) => after,
+            None => &rest[value_end..],
+        };
+
+        tags.push((key, value));
+    }
+
+    tags
+}
+
+/// Removes characters that would break a tag value in a generated .sm file
+pub(super) fn sm_escape(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !matches!(c, ';' | '#'))
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Same as `sm_escape`, but also strips colons for the fields of a #NOTES header
+pub(super) fn sm_escape_field(value: &str) -> String {
+    sm_escape(value).replace(':', "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unterminated_values_end_at_the_next_tag() {
+        let tags = parse_tags("#TITLE:No terminator\n#ARTIST:Someone;\n#BPMS:0.000=120.000");
+        assert_eq!(
+            tags,
+            vec![
+                ("TITLE".to_string(), "No terminator".to_string()),
+                ("ARTIST".to_string(), "Someone".to_string()),
+                ("BPMS".to_string(), "0.000=120.000".to_string()),
+            ]
+        );
+    }
+}
@@ -1,165 +1,147 @@
-use super::types::PacksResponse;
-use super::utils::SortField;
+use super::catalog::Catalog;
+use super::client::EtternaOnlineClient;
+use super::filters::PackFilters;
+use super::types::{DataResponse, Pack, PackDetails, PackSongsResponse, PacksResponse};
+use super::utils::{Sort, SortDirection, SortField, SortKey};
+use crate::error::AppError;
 use serde::Serialize;
 
+/// Song pages fetched at most for one pack, in case the API misreports `last_page`
+const MAX_SONG_PAGES: u64 = 100;
+
 #[derive(Debug, Serialize)]
 pub struct SortOption {
     pub value: String,
     pub label: String,
 }
 
+/// Fields and directions the frontend can build sort keys from
+#[derive(Debug, Serialize)]
+pub struct SortOptions {
+    pub fields: Vec<SortOption>,
+    pub directions: Vec<SortOption>,
+}
+
 #[tauri::command]
-pub fn get_sort_options() -> Vec<SortOption> {
-    vec![
-        SortOption {
-            value: "name".to_string(),
-            label: "Name".to_string(),
-        },
-        SortOption {
-            value: "popularity".to_string(),
-            label: "Popularity".to_string(),
-        },
-        SortOption {
-            value: "overall".to_string(),
-            label: "Overall".to_string(),
-        },
-        SortOption {
-            value: "stream".to_string(),
-            label: "Stream".to_string(),
-        },
-        SortOption {
-            value: "jumpstream".to_string(),
-            label: "Jumpstream".to_string(),
-        },
-        SortOption {
-            value: "handstream".to_string(),
-            label: "Handstream".to_string(),
-        },
-        SortOption {
-            value: "jacks".to_string(),
-            label: "Jacks".to_string(),
-        },
-        SortOption {
-            value: "chordjacks".to_string(),
-            label: "Chordjacks".to_string(),
-        },
-        SortOption {
-            value: "stamina".to_string(),
-            label: "Stamina".to_string(),
-        },
-        SortOption {
-            value: "technical".to_string(),
-            label: "Technical".to_string(),
-        },
-    ]
+pub fn get_sort_options() -> SortOptions {
+    SortOptions {
+        fields: SortField::ALL
+            .into_iter()
+            .map(|field| SortOption {
+                value: field.as_str().to_string(),
+                label: field.label().to_string(),
+            })
+            .collect(),
+        directions: SortDirection::ALL
+            .into_iter()
+            .map(|direction| SortOption {
+                value: direction.as_str().to_string(),
+                label: direction.label().to_string(),
+            })
+            .collect(),
+    }
 }
 
 #[tauri::command]
+#[tracing::instrument(skip(client, catalog))]
 pub async fn fetch_packs(
+    client: tauri::State<'_, EtternaOnlineClient>,
+    catalog: tauri::State<'_, Catalog>,
     page: Option<u64>,
     limit: Option<u64>,
-    sort: Option<String>,
+    sort: Option<Vec<SortKey>>,
     search: Option<String>,
-) -> Result<PacksResponse, String> {
-    println!("[fetch_packs] Starting fetch with params: page={:?}, limit={:?}, sort={:?}, search={:?}", page, limit, sort, search);
-    
-    let client = reqwest::Client::new();
-    
-    let mut url = reqwest::Url::parse("https://api.etternaonline.com/api/packs")
-        .map_err(|e| {
-            println!("[fetch_packs] URL parse error: {}", e);
-            format!("URL parse error: {}", e)
-        })?;
-    
-    println!("[fetch_packs] Base URL parsed successfully");
-    
+    filters: Option<PackFilters>,
+) -> Result<PacksResponse, AppError> {
+    tracing::debug!(
+        "Starting fetch with params: page={:?}, limit={:?}, sort={:?}, search={:?}",
+        page,
+        limit,
+        sort,
+        search
+    );
+
+    let filters = filters.unwrap_or_default();
+    filters.validate()?;
+
+    // Filtering pages the API can't filter leaves them short, the synced catalog gives exact pages
+    if filters.needs_local_filtering() && !catalog.is_empty() {
+        tracing::debug!("Filters not supported by the API, searching the catalog");
+        return catalog.search(page, limit, sort, search.as_deref(), &filters);
+    }
+
+    let mut url = client.endpoint("packs")?;
+
     if let Some(p) = page {
         url.query_pairs_mut().append_pair("page", &p.to_string());
-        println!("[fetch_packs] Added page parameter: {}", p);
+        tracing::debug!("Added page parameter: {}", p);
     }
     if let Some(l) = limit {
         url.query_pairs_mut().append_pair("limit", &l.to_string());
-        println!("[fetch_packs] Added limit parameter: {}", l);
+        tracing::debug!("Added limit parameter: {}", l);
     }
-    if let Some(s) = sort {
-        println!("[fetch_packs] Validating sort parameter: {}", s);
-        let sort_str = s.trim();
-        let is_descending = sort_str.starts_with('-');
-        let field_str = if is_descending {
-            &sort_str[1..]
-        } else {
-            sort_str
-        };
-        
-        if SortField::from_str(field_str).is_none() {
-            let error_msg = format!(
-                "Invalid sort field: {}. Valid options: name, popularity, overall, stream, jumpstream, handstream, jacks, chordjacks, stamina, technical",
-                field_str
-            );
-            println!("[fetch_packs] {}", error_msg);
-            return Err(error_msg);
-        }
-        
-        url.query_pairs_mut().append_pair("sort", &s);
-        println!("[fetch_packs] Added sort parameter: {}", s);
+    if let Some(keys) = sort {
+        let sort = Sort::new(keys).inspect_err(|e| tracing::warn!("{}", e))?;
+
+        url.query_pairs_mut().append_pair("sort", &sort.to_string());
+        tracing::debug!("Added sort parameter: {}", sort);
     }
     if let Some(search_term) = search {
         if !search_term.trim().is_empty() {
-            url.query_pairs_mut().append_pair("filter[search]", &search_term.trim());
-            println!("[fetch_packs] Added search parameter: {}", search_term.trim());
+            url.query_pairs_mut()
+                .append_pair("filter[search]", search_term.trim());
+            tracing::debug!("Added search parameter: {}", search_term.trim());
         }
     }
-    
-    let final_url = url.as_str();
-    println!("[fetch_packs] Final URL: {}", final_url);
-    
-    println!("[fetch_packs] Sending HTTP request...");
-    let response = client
-        .get(final_url)
-        .header("Accept", "application/json, text/plain, */*")
-        .header("Origin", "https://etternaonline.com")
-        .send()
-        .await
-        .map_err(|e| {
-            println!("[fetch_packs] Connection error: {}", e);
-            format!("Connection error: {}", e)
-        })?;
-    
-    let status = response.status();
-    println!("[fetch_packs] Response status: {}", status);
-    
-    if !status.is_success() {
-        let error_msg = format!("HTTP error: {}", status);
-        println!("[fetch_packs] {}", error_msg);
-        return Err(error_msg);
+
+    filters.append_to_query(&mut url);
+
+    tracing::debug!("Final URL: {}", url);
+
+    let mut packs_response: PacksResponse = client.get_json(&url).await?;
+
+    tracing::info!("Successfully parsed {} packs", packs_response.data.len());
+
+    // Filters the API doesn't support are applied to the page
+    filters.apply_to_page(&mut packs_response);
+    if packs_response.meta.filtered_out > 0 {
+        tracing::debug!("Filtered out {} packs", packs_response.meta.filtered_out);
     }
-    
-    println!("[fetch_packs] Reading response body...");
-    let response_text = response
-        .text()
-        .await
-        .map_err(|e| {
-            println!("[fetch_packs] Error reading response body: {}", e);
-            format!("Error reading response body: {}", e)
-        })?;
-    
-    println!("[fetch_packs] Response body length: {} bytes", response_text.len());
-    println!("[fetch_packs] Response body preview (first 500 chars): {}", 
-        if response_text.len() > 500 {
-            &response_text[..500]
-        } else {
-            &response_text
-        });
-    
-    println!("[fetch_packs] Parsing JSON response...");
-    let packs_response: PacksResponse = serde_json::from_str(&response_text)
-        .map_err(|e| {
-            println!("[fetch_packs] JSON parse error: {}", e);
-            println!("[fetch_packs] Error at position: {:?}", e);
-            format!("JSON parse error: {}", e)
-        })?;
-    
-    println!("[fetch_packs] Successfully parsed {} packs", packs_response.data.len());
+
     Ok(packs_response)
 }
 
+/// Pack info and its song list, so a pack can be looked at before downloading it
+#[tauri::command]
+#[tracing::instrument(skip(client))]
+pub async fn fetch_pack_details(
+    client: tauri::State<'_, EtternaOnlineClient>,
+    pack_id: u64,
+) -> Result<PackDetails, AppError> {
+    let url = client.endpoint(&format!("packs/{}", pack_id))?;
+    let pack: DataResponse<Pack> = client.get_json(&url).await?;
+
+    // Songs are paginated, gather every page
+    let mut songs = Vec::new();
+    for page in 1..=MAX_SONG_PAGES {
+        let mut url = client.endpoint(&format!("packs/{}/songs", pack_id))?;
+        url.query_pairs_mut().append_pair("page", &page.to_string());
+        let response: PackSongsResponse = client.get_json(&url).await?;
+        let is_last =
+            response.data.is_empty() || response.meta.current_page >= response.meta.last_page;
+        songs.extend(response.data);
+
+        if is_last {
+            break;
+        }
+        if page == MAX_SONG_PAGES {
+            tracing::warn!("Stopped after {} pages of songs", MAX_SONG_PAGES);
+        }
+    }
+
+    tracing::info!("Fetched {} songs", songs.len());
+    Ok(PackDetails {
+        pack: pack.data,
+        songs,
+    })
+}
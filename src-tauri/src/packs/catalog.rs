@@ -0,0 +1,668 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+use tauri::{Emitter, Manager};
+
+use super::client::EtternaOnlineClient;
+use super::filters::PackFilters;
+use super::types::{Pack, PackLinks, PackMeta, PacksResponse};
+use super::utils::{Sort, SortField, SortKey};
+use crate::error::{AppError, ErrorKind};
+
+/// Packs asked per page while syncing
+const SYNC_PAGE_SIZE: u64 = 100;
+/// Age of the last sync after which the catalog is refreshed in the background, in seconds
+const MAX_AGE: u64 = 60 * 60;
+/// Delay before retrying a background refresh that failed
+const RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+/// Page size when none is given
+const DEFAULT_PAGE_SIZE: u64 = 12;
+
+/// The catalog as cached on disk
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct CatalogData {
+    /// Unix timestamp of the last sync that went through every page
+    synced_at: Option<u64>,
+    packs: Vec<Pack>,
+    /// Position of every pack in `packs`
+    #[serde(skip)]
+    index: HashMap<u64, usize>,
+}
+
+impl CatalogData {
+    fn reindex(&mut self) {
+        self.index = self
+            .packs
+            .iter()
+            .enumerate()
+            .map(|(position, pack)| (pack.id, position))
+            .collect();
+    }
+}
+
+/// Packs seen by a running sync
+#[derive(Debug, Default)]
+struct SyncState {
+    seen: HashSet<u64>,
+    /// New or changed packs merged so far
+    changed: usize,
+}
+
+/// State of the local catalog, also sent as the `catalog-status` event while syncing
+#[derive(Debug, Clone, Serialize)]
+pub struct CatalogStatus {
+    pub synced_at: Option<u64>,
+    pub pack_count: usize,
+    pub syncing: bool,
+    /// Pages fetched by the running sync
+    pub synced_pages: u64,
+    pub total_pages: u64,
+}
+
+/// Local copy of the EtternaOnline pack list, searched without a network round-trip
+#[derive(Default)]
+pub struct Catalog {
+    data: RwLock<CatalogData>,
+    syncing: AtomicBool,
+}
+
+impl Catalog {
+    /// Reads the cached catalog, an unreadable cache starts empty
+    pub fn load() -> Self {
+        let mut data = Self::get_catalog_path()
+            .and_then(|path| {
+                if !path.exists() {
+                    return Ok(CatalogData::default());
+                }
+                let content = std::fs::read_to_string(&path)
+                    .map_err(|e| AppError::io("Error reading catalog", e))?;
+                serde_json::from_str(&content).map_err(|e| {
+                    AppError::new(ErrorKind::Io, format!("Error parsing catalog: {}", e))
+                        .with_details(path.display().to_string())
+                })
+            })
+            .unwrap_or_else(|e| {
+                tracing::warn!("{}, starting with an empty catalog", e);
+                CatalogData::default()
+            });
+        data.reindex();
+        tracing::debug!("Loaded {} packs from the catalog", data.packs.len());
+
+        Catalog {
+            data: RwLock::new(data),
+            syncing: AtomicBool::new(false),
+        }
+    }
+
+    fn get_catalog_path() -> Result<PathBuf, AppError> {
+        Ok(crate::paths::cache_dir()?.join("catalog.json"))
+    }
+
+    fn save(&self) -> Result<(), AppError> {
+        let catalog_path = Self::get_catalog_path()?;
+        let content = serde_json::to_string(&*self.read()).map_err(|e| {
+            AppError::new(ErrorKind::Io, format!("Error serializing catalog: {}", e))
+        })?;
+
+        let tmp_path = catalog_path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, content).map_err(|e| AppError::io("Error writing catalog", e))?;
+        std::fs::rename(&tmp_path, &catalog_path)
+            .map_err(|e| AppError::io("Error writing catalog", e))?;
+
+        Ok(())
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, CatalogData> {
+        self.data.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, CatalogData> {
+        self.data.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn status(&self) -> CatalogStatus {
+        let data = self.read();
+        CatalogStatus {
+            synced_at: data.synced_at,
+            pack_count: data.packs.len(),
+            syncing: self.syncing.load(Ordering::SeqCst),
+            synced_pages: 0,
+            total_pages: 0,
+        }
+    }
+
+    /// Time left before the catalog is older than `MAX_AGE`
+    fn time_until_refresh(&self) -> std::time::Duration {
+        let age = self.read().synced_at.map_or(MAX_AGE, |synced_at| {
+            crate::library::now_timestamp().saturating_sub(synced_at)
+        });
+        std::time::Duration::from_secs(MAX_AGE.saturating_sub(age))
+    }
+
+    /// Adds a synced page, replacing the packs already known
+    /// Returns how many packs were new or changed
+    fn merge(&self, packs: Vec<Pack>) -> usize {
+        let mut data = self.write();
+        let mut changed = 0;
+        for pack in packs {
+            match data.index.get(&pack.id).copied() {
+                Some(position) if data.packs[position] == pack => {}
+                Some(position) => {
+                    data.packs[position] = pack;
+                    changed += 1;
+                }
+                None => {
+                    let position = data.packs.len();
+                    data.index.insert(pack.id, position);
+                    data.packs.push(pack);
+                    changed += 1;
+                }
+            }
+        }
+        changed
+    }
+
+    /// Drops the packs a complete sync did not see, they were removed from EtternaOnline
+    /// A pack moving across a page boundary during the sync is seen twice or not at all,
+    /// so nothing is dropped unless as many packs were seen as the API counts
+    fn finish_sync(&self, seen: &HashSet<u64>, total: u64) {
+        let mut data = self.write();
+        if seen.len() as u64 == total {
+            data.packs.retain(|pack| seen.contains(&pack.id));
+            data.reindex();
+        } else {
+            tracing::info!(
+                "Saw {} of {} packs, keeping the packs that were not seen",
+                seen.len(),
+                total
+            );
+        }
+        data.synced_at = Some(crate::library::now_timestamp());
+    }
+
+    /// Merges a synced page, finishing the sync on the last one
+    /// Returns whether the page was the last one
+    fn sync_page(&self, state: &mut SyncState, response: PacksResponse) -> bool {
+        state.seen.extend(response.data.iter().map(|pack| pack.id));
+        state.changed += self.merge(response.data);
+
+        let is_last = response.meta.current_page >= response.meta.last_page;
+        if is_last {
+            self.finish_sync(&state.seen, response.meta.total);
+        }
+        is_last
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.read().packs.is_empty()
+    }
+
+    /// Same as `fetch_packs`, served from the catalog, expects validated filters
+    pub fn search(
+        &self,
+        page: Option<u64>,
+        limit: Option<u64>,
+        sort: Option<Vec<SortKey>>,
+        search: Option<&str>,
+        filters: &PackFilters,
+    ) -> Result<PacksResponse, AppError> {
+        let sort = sort.map(Sort::new).transpose()?;
+        let response = self.query(
+            page.unwrap_or(1),
+            limit.unwrap_or(DEFAULT_PAGE_SIZE),
+            sort.as_ref(),
+            search.unwrap_or_default(),
+            filters,
+        );
+        tracing::debug!("{} packs match", response.meta.total);
+        Ok(response)
+    }
+
+    /// A page of the packs matching the search and filters, in the same shape as the API
+    fn query(
+        &self,
+        page: u64,
+        limit: u64,
+        sort: Option<&Sort>,
+        search: &str,
+        filters: &PackFilters,
+    ) -> PacksResponse {
+        let terms = SearchTerm::parse(search);
+        let data = self.read();
+        let mut matches: Vec<(u32, &Pack)> = data
+            .packs
+            .iter()
+            .filter(|pack| filters.matches(pack))
+            .filter_map(|pack| search_score(pack, &terms).map(|score| (score, pack)))
+            .collect();
+
+        // Without a sort, the best matches come first
+        match sort {
+            Some(sort) => matches.sort_by(|(_, a), (_, b)| sort.compare(a, b)),
+            None => matches.sort_by(|(a_score, a), (b_score, b)| {
+                b_score
+                    .cmp(a_score)
+                    .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+            }),
+        }
+
+        let total = matches.len() as u64;
+        let limit = limit.max(1);
+        let last_page = total.div_ceil(limit).max(1);
+        let page = page.clamp(1, last_page);
+        let start = (page - 1) * limit;
+        let packs: Vec<Pack> = matches
+            .into_iter()
+            .skip(start as usize)
+            .take(limit as usize)
+            .map(|(_, pack)| pack.clone())
+            .collect();
+
+        PacksResponse {
+            meta: PackMeta {
+                current_page: page,
+                from: (!packs.is_empty()).then_some(start + 1),
+                last_page,
+                links: Vec::new(),
+                path: "catalog".to_string(),
+                per_page: limit,
+                to: (!packs.is_empty()).then_some(start + packs.len() as u64),
+                total,
+                filtered_out: 0,
+                totals_are_estimates: false,
+            },
+            links: PackLinks {
+                first: String::new(),
+                last: String::new(),
+                prev: None,
+                next: None,
+            },
+            data: packs,
+        }
+    }
+}
+
+/// A word of a catalog search, `tag:name` only looks at the tags
+#[derive(Debug, PartialEq)]
+enum SearchTerm {
+    Text(String),
+    Tag(String),
+}
+
+impl SearchTerm {
+    fn parse(search: &str) -> Vec<SearchTerm> {
+        search
+            .split_whitespace()
+            .map(|word| word.to_lowercase())
+            .filter_map(|word| match word.strip_prefix("tag:") {
+                Some("") => None,
+                Some(tag) => Some(SearchTerm::Tag(tag.to_string())),
+                None => Some(SearchTerm::Text(word)),
+            })
+            .collect()
+    }
+}
+
+/// How well a pack matches every term, `None` when a term matches nothing
+/// Name matches rank above tag matches, which rank above typo-tolerant matches
+fn search_score(pack: &Pack, terms: &[SearchTerm]) -> Option<u32> {
+    let name = pack.name.to_lowercase();
+    let words: Vec<&str> = name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+    let tags: Vec<String> = pack
+        .tags
+        .iter()
+        .map(|tag| tag.name.to_lowercase())
+        .collect();
+
+    terms.iter().try_fold(0, |score, term| {
+        let term_score = match term {
+            SearchTerm::Tag(tag) => tags
+                .iter()
+                .any(|name| name.contains(tag.as_str()))
+                .then_some(2),
+            SearchTerm::Text(text) => {
+                if words.iter().any(|word| word.starts_with(text.as_str())) {
+                    Some(4)
+                } else if name.contains(text.as_str()) {
+                    Some(3)
+                } else if tags.iter().any(|name| name.contains(text.as_str())) {
+                    Some(2)
+                } else {
+                    let max_distance = match text.chars().count() {
+                        0..=3 => 0,
+                        4..=7 => 1,
+                        _ => 2,
+                    };
+                    (max_distance > 0
+                        && words
+                            .iter()
+                            .any(|word| edit_distance(word, text) <= max_distance))
+                    .then_some(1)
+                }
+            }
+        };
+        term_score.map(|term_score| score + term_score)
+    })
+}
+
+/// Levenshtein distance between two words
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+fn emit_status(app: &tauri::AppHandle, status: CatalogStatus) {
+    let _ = app.emit("catalog-status", status);
+}
+
+/// Fetches every page of the pack list into the catalog
+/// Pages are merged as they arrive, so searches see them during the sync
+/// The API can't list packs by update date, so edits to any pack are only seen by going through every page
+pub async fn sync(
+    app: &tauri::AppHandle,
+    catalog: &Catalog,
+    client: &EtternaOnlineClient,
+) -> Result<CatalogStatus, AppError> {
+    if catalog.syncing.swap(true, Ordering::SeqCst) {
+        return Err(AppError::new(
+            ErrorKind::Conflict,
+            "The catalog is already syncing",
+        ));
+    }
+
+    let result = sync_pages(app, catalog, client).await;
+    catalog.syncing.store(false, Ordering::SeqCst);
+
+    // Pages merged before a failure are kept
+    if let Err(e) = catalog.save() {
+        tracing::warn!("Error saving catalog: {}", e);
+    }
+    let status = catalog.status();
+    emit_status(app, status.clone());
+
+    match result {
+        Ok(changed) => {
+            tracing::info!(
+                "Catalog synced, {} packs ({} new or changed)",
+                status.pack_count,
+                changed
+            );
+            Ok(status)
+        }
+        Err(e) => {
+            tracing::warn!("Catalog sync failed: {}", e);
+            Err(e)
+        }
+    }
+}
+
+/// Returns how many packs were new or changed
+async fn sync_pages(
+    app: &tauri::AppHandle,
+    catalog: &Catalog,
+    client: &EtternaOnlineClient,
+) -> Result<usize, AppError> {
+    let mut state = SyncState::default();
+    let mut page = 1;
+    loop {
+        let mut url = client.endpoint("packs")?;
+        url.query_pairs_mut()
+            .append_pair("page", &page.to_string())
+            .append_pair("limit", &SYNC_PAGE_SIZE.to_string())
+            .append_pair("sort", SortField::Name.as_str());
+        let response: PacksResponse = client.get_json(&url).await?;
+
+        let total_pages = response.meta.last_page;
+        let is_last = catalog.sync_page(&mut state, response);
+        emit_status(
+            app,
+            CatalogStatus {
+                synced_pages: page,
+                total_pages,
+                ..catalog.status()
+            },
+        );
+
+        if is_last {
+            return Ok(state.changed);
+        }
+        page += 1;
+    }
+}
+
+/// Keeps the catalog up to date without holding up startup,
+/// syncing again once the last sync is older than `MAX_AGE`
+pub fn refresh_in_background(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let catalog = app.state::<Catalog>();
+        let client = app.state::<EtternaOnlineClient>();
+        loop {
+            tokio::time::sleep(catalog.time_until_refresh()).await;
+            // Failures are logged, the cached catalog stays usable
+            if sync(&app, &catalog, &client).await.is_err() {
+                tokio::time::sleep(RETRY_DELAY).await;
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_catalog_status(catalog: tauri::State<'_, Catalog>) -> CatalogStatus {
+    catalog.status()
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn sync_catalog(
+    app: tauri::AppHandle,
+    catalog: tauri::State<'_, Catalog>,
+    client: tauri::State<'_, EtternaOnlineClient>,
+) -> Result<CatalogStatus, AppError> {
+    sync(&app, &catalog, &client).await
+}
+
+/// Same as `fetch_packs`, served from the local catalog
+/// `tag:name` words only match tags, other words match the name or tags and tolerate typos
+#[tauri::command]
+#[tracing::instrument(skip(catalog))]
+pub fn search_catalog(
+    catalog: tauri::State<'_, Catalog>,
+    page: Option<u64>,
+    limit: Option<u64>,
+    sort: Option<Vec<SortKey>>,
+    search: Option<String>,
+    filters: Option<PackFilters>,
+) -> Result<PacksResponse, AppError> {
+    let filters = filters.unwrap_or_default();
+    filters.validate()?;
+    catalog.search(page, limit, sort, search.as_deref(), &filters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pack(id: u64, name: &str, overall: f64, tags: &[&str]) -> Pack {
+        Pack {
+            play_count: id,
+            overall,
+            ..Pack::fixture(id, name, tags)
+        }
+    }
+
+    fn catalog() -> Catalog {
+        let catalog = Catalog::default();
+        catalog.merge(vec![
+            pack(1, "Jumpstream Madness", 24.0, &["Dumps"]),
+            pack(2, "Stream Pack 3", 20.0, &["Stream"]),
+            pack(3, "Chordjack Collection", 27.0, &["Jacks"]),
+            pack(4, "Easy Stuff", 12.0, &["Beginner"]),
+        ]);
+        catalog
+    }
+
+    fn names(response: &PacksResponse) -> Vec<&str> {
+        response
+            .data
+            .iter()
+            .map(|pack| pack.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn pages_are_sorted_locally() {
+        let catalog = catalog();
+        let sort: Sort = "-overall".parse().unwrap();
+
+        let first = catalog.query(1, 3, Some(&sort), "", &PackFilters::default());
+        assert_eq!(
+            names(&first),
+            vec![
+                "Chordjack Collection",
+                "Jumpstream Madness",
+                "Stream Pack 3"
+            ]
+        );
+        assert_eq!((first.meta.total, first.meta.last_page), (4, 2));
+
+        let second = catalog.query(2, 3, Some(&sort), "", &PackFilters::default());
+        assert_eq!(names(&second), vec!["Easy Stuff"]);
+        assert_eq!((second.meta.from, second.meta.to), (Some(4), Some(4)));
+
+        // Ties on the first key are broken by the next one
+        catalog.merge(vec![pack(5, "Another Chordjack Pack", 27.0, &[])]);
+        let sort: Sort = "-overall,name".parse().unwrap();
+        let first = catalog.query(1, 2, Some(&sort), "", &PackFilters::default());
+        assert_eq!(
+            names(&first),
+            vec!["Another Chordjack Pack", "Chordjack Collection"]
+        );
+    }
+
+    #[test]
+    fn search_matches_names_tags_and_typos() {
+        let catalog = catalog();
+        let search =
+            |text| names(&catalog.query(1, 10, None, text, &PackFilters::default())).join(", ");
+
+        // Word prefixes rank above substrings
+        assert_eq!(search("stream"), "Stream Pack 3, Jumpstream Madness");
+        assert_eq!(search("tag:dumps"), "Jumpstream Madness");
+        assert_eq!(search("beginner"), "Easy Stuff");
+        assert_eq!(search("chordjak colection"), "Chordjack Collection");
+        assert_eq!(search("stream tag:jacks"), "");
+    }
+
+    #[test]
+    fn merges_count_new_and_changed_packs() {
+        let catalog = catalog();
+        assert_eq!(
+            catalog.merge(vec![pack(1, "Jumpstream Madness", 24.0, &["Dumps"])]),
+            0
+        );
+        assert_eq!(
+            catalog.merge(vec![
+                pack(2, "Stream Pack 3 (Fixed)", 20.0, &["Stream"]),
+                pack(5, "New Pack", 18.0, &[]),
+            ]),
+            2
+        );
+
+        let data = catalog.read();
+        assert_eq!(data.packs.len(), 5);
+        assert_eq!(data.packs[data.index[&2]].name, "Stream Pack 3 (Fixed)");
+        assert_eq!(data.packs[data.index[&5]].name, "New Pack");
+    }
+
+    #[test]
+    fn complete_sync_drops_removed_packs() {
+        let catalog = catalog();
+        catalog.merge(vec![pack(2, "Stream Pack 3 (Fixed)", 20.0, &[])]);
+        catalog.finish_sync(&HashSet::from([1, 2]), 2);
+
+        let status = catalog.status();
+        assert_eq!(status.pack_count, 2);
+        assert!(status.synced_at.is_some());
+        assert!(catalog.time_until_refresh() > std::time::Duration::ZERO);
+        assert_eq!(catalog.read().packs[1].name, "Stream Pack 3 (Fixed)");
+
+        // Merging after the packs moved keeps the index right
+        catalog.merge(vec![pack(2, "Stream Pack 3", 20.0, &[])]);
+        assert_eq!(catalog.read().packs[1].name, "Stream Pack 3");
+    }
+
+    #[test]
+    fn sync_missing_packs_keeps_them() {
+        let catalog = catalog();
+        // Pack 4 shifted to another page while syncing, and pack 2 was seen twice
+        catalog.finish_sync(&HashSet::from([1, 2, 3]), 4);
+
+        assert_eq!(catalog.status().pack_count, 4);
+        assert!(catalog.status().synced_at.is_some());
+    }
+
+    /// Page of a sync, in the shape the API sends it
+    fn sync_response(
+        current_page: u64,
+        last_page: u64,
+        total: u64,
+        packs: Vec<Pack>,
+    ) -> PacksResponse {
+        let mut response = Catalog::default().query(1, 1, None, "", &PackFilters::default());
+        response.meta.current_page = current_page;
+        response.meta.last_page = last_page;
+        response.meta.total = total;
+        response.data = packs;
+        response
+    }
+
+    #[test]
+    fn sync_goes_past_unchanged_pages() {
+        let catalog = catalog();
+        let mut state = SyncState::default();
+
+        // Nothing changed on the first page, an older pack was retagged on the last one
+        let unchanged = sync_response(
+            1,
+            2,
+            4,
+            vec![
+                pack(1, "Jumpstream Madness", 24.0, &["Dumps"]),
+                pack(2, "Stream Pack 3", 20.0, &["Stream"]),
+            ],
+        );
+        assert!(!catalog.sync_page(&mut state, unchanged));
+        assert_eq!(state.changed, 0);
+        assert!(catalog.status().synced_at.is_none());
+
+        let retagged = sync_response(
+            2,
+            2,
+            4,
+            vec![
+                pack(3, "Chordjack Collection", 27.0, &["Jacks", "Technical"]),
+                pack(4, "Easy Stuff", 12.0, &["Beginner"]),
+            ],
+        );
+        assert!(catalog.sync_page(&mut state, retagged));
+        assert_eq!(state.changed, 1);
+        assert!(catalog.status().synced_at.is_some());
+
+        let data = catalog.read();
+        assert_eq!(data.packs[data.index[&3]].tags.len(), 2);
+    }
+}
@@ -0,0 +1,269 @@
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, ORIGIN, RETRY_AFTER};
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+
+use crate::error::{AppError, ErrorKind};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.etternaonline.com/api/";
+
+/// Longest wait accepted from a Retry-After header
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// How the EtternaOnline client connects, the defaults target the public API
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// Root of the API, endpoints such as `packs` are joined to it
+    pub base_url: String,
+    /// Sent as the Origin header, like the website does
+    pub origin: String,
+    pub user_agent: String,
+    pub connect_timeout: Duration,
+    /// Longest wait for a chunk of data, the only limit on pack downloads
+    pub read_timeout: Duration,
+    /// Limit for a whole API request
+    pub request_timeout: Duration,
+    /// Retries after a connection error, a 5xx or a 429
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled on each attempt
+    pub retry_backoff: Duration,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        ClientConfig {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            origin: "https://etternaonline.com".to_string(),
+            user_agent: concat!("rOtterna/", env!("CARGO_PKG_VERSION")).to_string(),
+            connect_timeout: Duration::from_secs(10),
+            read_timeout: Duration::from_secs(30),
+            request_timeout: Duration::from_secs(30),
+            max_retries: 3,
+            retry_backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+/// EtternaOnline API client, shared through the Tauri state so connections are pooled
+pub struct EtternaOnlineClient {
+    http: reqwest::Client,
+    base_url: reqwest::Url,
+    request_timeout: Duration,
+    max_retries: u32,
+    retry_backoff: Duration,
+}
+
+impl EtternaOnlineClient {
+    pub fn new(config: ClientConfig) -> Result<Self, AppError> {
+        let mut base_url = reqwest::Url::parse(&config.base_url).map_err(|e| {
+            AppError::new(ErrorKind::InvalidInput, format!("Invalid API URL: {}", e))
+                .with_details(&config.base_url)
+        })?;
+        // Without a trailing slash, joining an endpoint would replace the last segment
+        if !base_url.path().ends_with('/') {
+            base_url.set_path(&format!("{}/", base_url.path()));
+        }
+
+        let mut headers = HeaderMap::new();
+        let origin = HeaderValue::from_str(&config.origin).map_err(|e| {
+            AppError::new(ErrorKind::InvalidInput, format!("Invalid origin: {}", e))
+        })?;
+        headers.insert(ORIGIN, origin);
+
+        let http = reqwest::Client::builder()
+            .user_agent(config.user_agent)
+            .default_headers(headers)
+            .connect_timeout(config.connect_timeout)
+            .read_timeout(config.read_timeout)
+            .build()
+            .map_err(|e| AppError::network("Error creating HTTP client", e))?;
+
+        Ok(EtternaOnlineClient {
+            http,
+            base_url,
+            request_timeout: config.request_timeout,
+            max_retries: config.max_retries,
+            retry_backoff: config.retry_backoff,
+        })
+    }
+
+    /// The pooled HTTP client, also used to download the pack archives
+    pub fn http(&self) -> &reqwest::Client {
+        &self.http
+    }
+
+    /// URL of an API endpoint, relative to the base URL
+    pub fn endpoint(&self, path: &str) -> Result<reqwest::Url, AppError> {
+        self.base_url.join(path).map_err(|e| {
+            AppError::new(ErrorKind::InvalidInput, format!("URL parse error: {}", e))
+                .with_details(path)
+        })
+    }
+
+    /// GETs a JSON document, retrying transient failures
+    pub async fn get_json<T: DeserializeOwned>(&self, url: &reqwest::Url) -> Result<T, AppError> {
+        let response = self.send_with_retry(url).await?;
+
+        let response_text = response.text().await.map_err(|e| {
+            tracing::error!("Error reading response body: {}", e);
+            AppError::network("Error reading response body", e)
+        })?;
+
+        tracing::debug!("Response body length: {} bytes", response_text.len());
+        tracing::trace!(
+            "Response body preview (first 500 chars): {}",
+            response_text.get(..500).unwrap_or(&response_text)
+        );
+
+        serde_json::from_str(&response_text).map_err(|e| {
+            tracing::error!("JSON parse error: {}", e);
+            AppError::new(
+                ErrorKind::InvalidResponse,
+                format!("JSON parse error: {}", e),
+            )
+            .with_details(url.as_str())
+        })
+    }
+
+    /// Sends a GET, retrying with exponential backoff after a connection error, a 5xx or a 429
+    async fn send_with_retry(&self, url: &reqwest::Url) -> Result<reqwest::Response, AppError> {
+        let mut attempt = 0;
+        loop {
+            tracing::debug!("GET {}", url);
+            let result = self
+                .http
+                .get(url.clone())
+                .header(ACCEPT, "application/json, text/plain, */*")
+                .timeout(self.request_timeout)
+                .send()
+                .await;
+
+            let (error, retry_after) = match result {
+                Ok(response) if response.status().is_success() => return Ok(response),
+                Ok(response) => {
+                    let status = response.status();
+                    let error = AppError::http(status).with_details(url.as_str());
+                    if !(status.is_server_error() || error.is(ErrorKind::RateLimited)) {
+                        return Err(error);
+                    }
+                    (error, retry_after(&response))
+                }
+                Err(e) => {
+                    let transient = e.is_connect() || e.is_timeout();
+                    let error = AppError::network("Connection error", e).with_details(url.as_str());
+                    if !transient {
+                        return Err(error);
+                    }
+                    (error, None)
+                }
+            };
+
+            if attempt >= self.max_retries {
+                tracing::error!("{}, giving up after {} retries", error, attempt);
+                return Err(error);
+            }
+            let delay = retry_after.unwrap_or(self.retry_backoff * 2u32.pow(attempt));
+            attempt += 1;
+            tracing::warn!(
+                "{}, retrying in {:?} ({}/{})",
+                error,
+                delay,
+                attempt,
+                self.max_retries
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// Delay asked by the server, in seconds
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let seconds: u64 = response
+        .headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(seconds).min(MAX_RETRY_DELAY))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    /// Answers each request with the next of `responses` (status, body), recording the paths
+    fn serve(responses: Vec<(u16, &'static str)>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}/api", listener.local_addr().unwrap());
+        let paths = Arc::new(Mutex::new(Vec::new()));
+        let recorded = paths.clone();
+
+        std::thread::spawn(move || {
+            for (status, body) in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = [0u8; 4096];
+                let read = stream.read(&mut request).unwrap();
+                let request = String::from_utf8_lossy(&request[..read]).to_string();
+                let path = request.split_whitespace().nth(1).unwrap_or_default();
+                recorded.lock().unwrap().push(path.to_string());
+
+                let response = format!(
+                    "HTTP/1.1 {} Status\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        (base_url, paths)
+    }
+
+    fn test_client(base_url: String) -> EtternaOnlineClient {
+        EtternaOnlineClient::new(ClientConfig {
+            base_url,
+            max_retries: 2,
+            retry_backoff: Duration::from_millis(1),
+            ..ClientConfig::default()
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_retried() {
+        let (base_url, paths) = serve(vec![(503, ""), (429, ""), (200, "{\"ok\":true}")]);
+        let client = test_client(base_url);
+
+        let url = client.endpoint("packs?page=2").unwrap();
+        let value: serde_json::Value = client.get_json(&url).await.unwrap();
+
+        assert_eq!(value, serde_json::json!({ "ok": true }));
+        assert_eq!(*paths.lock().unwrap(), vec!["/api/packs?page=2"; 3]);
+    }
+
+    #[tokio::test]
+    async fn client_errors_and_exhausted_retries_fail() {
+        let (base_url, paths) = serve(vec![(404, "")]);
+        let client = test_client(base_url);
+        let error = client
+            .get_json::<serde_json::Value>(&client.endpoint("packs/1").unwrap())
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Http);
+        assert_eq!(paths.lock().unwrap().len(), 1);
+
+        let (base_url, paths) = serve(vec![(500, ""), (502, ""), (503, "")]);
+        let client = test_client(base_url);
+        let error = client
+            .get_json::<serde_json::Value>(&client.endpoint("packs").unwrap())
+            .await
+            .unwrap_err();
+        assert_eq!(error.message, "HTTP error: 503 Service Unavailable");
+        assert_eq!(paths.lock().unwrap().len(), 3);
+    }
+}
@@ -1,369 +1,1185 @@
-use tauri::Emitter;
-
-fn emit_progress(app: &tauri::AppHandle, pack_id: u64, downloaded: u64, total: u64, stage: &str) {
-    let event_name = format!("download-progress-{}", pack_id);
-    let _ = app.emit(
-        &event_name,
-        serde_json::json!({
-            "packId": pack_id,
-            "downloaded": downloaded,
-            "total": total,
-            "stage": stage
-        }),
-    );
+use tauri::{Emitter, Manager};
+
+use super::client::EtternaOnlineClient;
+use super::jobs::{CancelToken, DownloadJobs};
+use super::queue::{emit_queue, DownloadQueue};
+use super::types::{
+    ArchiveStatus, ConversionFailure, ConversionFailureReason, DownloadProgress, DownloadStage,
+    InstallReport, SongFolderConflict,
+};
+use crate::error::{AppError, ErrorKind};
+
+/// Minimum delay between two extraction or conversion progress events
+const PROGRESS_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+fn emit_progress(app: &tauri::AppHandle, progress: DownloadProgress) {
+    let event_name = format!("download-progress-{}", progress.pack_id);
+    let _ = app.emit(&event_name, progress);
 }
 
 #[tauri::command]
+#[tracing::instrument(skip(app, jobs, queue, download_url))]
 pub async fn download_pack(
     app: tauri::AppHandle,
+    jobs: tauri::State<'_, DownloadJobs>,
+    queue: tauri::State<'_, DownloadQueue>,
     download_url: String,
     pack_id: u64,
-) -> Result<String, String> {
-    println!("[download_pack] Starting download from: {}", download_url);
-    
-    // Emit initial progress
-    let _ = app.emit(
-        &format!("download-progress-{}", pack_id),
-        serde_json::json!({
-            "packId": pack_id,
-            "downloaded": 0,
-            "total": 0,
-            "stage": "downloading"
-        }),
-    );
-    
-    // Download the ZIP file
-    let zip_path = download_file(&app, &download_url, pack_id).await?;
-    
-    // Emit extracting stage
-    emit_progress(&app, pack_id, 100, 100, "extracting");
-    
-    // Extract the ZIP file
-    let extract_path = extract_zip(&zip_path)?;
-    
-    // Emit converting stage
-    emit_progress(&app, pack_id, 100, 100, "converting");
-    
-    // Process all .sm files found in the extracted directory
-    process_sm_files(&extract_path)?;
-    
-    Ok(zip_path.to_string_lossy().to_string())
+    pack_name: Option<String>,
+) -> Result<InstallReport, AppError> {
+    let cancel = jobs.start(pack_id)?;
+    let zip_path = get_download_path(&download_url);
+    // A cancelled re-download must not remove the files of the installed pack
+    let existing = zip_path.as_deref().map(ExistingFiles::of);
+
+    queue.enqueue(pack_id, pack_name.clone());
+    emit_queue(&app, &queue);
+
+    let result = match &zip_path {
+        Ok(zip_path) => {
+            install_pack(
+                &app,
+                &queue,
+                &download_url,
+                pack_id,
+                pack_name,
+                zip_path,
+                &cancel,
+            )
+            .await
+        }
+        Err(e) => Err(e.clone()),
+    };
+
+    queue.remove(pack_id);
+    emit_queue(&app, &queue);
+    jobs.finish(pack_id);
+
+    if let (Err(e), Ok(zip_path), Ok(existing)) = (&result, &zip_path, &existing) {
+        if e.is(ErrorKind::Cancelled) {
+            tracing::info!("Download of pack {} cancelled, cleaning up", pack_id);
+            clean_up_cancelled(zip_path, existing);
+            emit_progress(
+                &app,
+                DownloadProgress::new(pack_id, DownloadStage::Cancelled, 0, 0),
+            );
+        }
+    }
+
+    result
 }
 
-/// Downloads a file from the given URL and saves it to the downloads directory
-async fn download_file(
+/// Waits for a download slot, then downloads, extracts and converts a pack,
+/// checking for cancellation between steps
+async fn install_pack(
     app: &tauri::AppHandle,
+    queue: &DownloadQueue,
     download_url: &str,
     pack_id: u64,
-) -> Result<std::path::PathBuf, String> {
-    println!("[download_file] Sending HTTP request...");
-    
-    let client = reqwest::Client::new();
-    let mut response = client
-        .get(download_url)
-        .header("Accept", "*/*")
-        .header("Origin", "https://etternaonline.com")
-        .send()
-        .await
-        .map_err(|e| {
-            println!("[download_file] Connection error: {}", e);
-            format!("Connection error: {}", e)
-        })?;
-    
-    let status = response.status();
-    println!("[download_file] Response status: {}", status);
-    
-    if !status.is_success() {
-        let error_msg = format!("HTTP error: {}", status);
-        println!("[download_file] {}", error_msg);
-        return Err(error_msg);
+    pack_name: Option<String>,
+    zip_path: &std::path::Path,
+    cancel: &CancelToken,
+) -> Result<InstallReport, AppError> {
+    emit_progress(
+        app,
+        DownloadProgress::new(pack_id, DownloadStage::Queued, 0, 0),
+    );
+    let client = app.state::<EtternaOnlineClient>();
+
+    // A paused download goes back to waiting and resumes from its .part file
+    loop {
+        let pause = queue.wait_for_turn(pack_id, cancel).await?;
+        emit_queue(app, queue);
+
+        tracing::info!("Starting download from: {}", download_url);
+
+        // Emit initial progress
+        emit_progress(
+            app,
+            DownloadProgress::new(pack_id, DownloadStage::Downloading, 0, 0),
+        );
+
+        // Download the ZIP file
+        let result = tokio::select! {
+            result = download_file(app, client.http(), download_url, pack_id, zip_path, cancel) => result,
+            _ = pause.cancelled() => Err(AppError::paused()),
+        };
+        match result {
+            Err(e) if e.is(ErrorKind::Paused) => {
+                tracing::info!("Download of pack {} paused", pack_id);
+                emit_progress(
+                    app,
+                    DownloadProgress::new(pack_id, DownloadStage::Paused, 0, 0),
+                );
+            }
+            result => {
+                result?;
+                break;
+            }
+        }
     }
-    
-    // Get content length if available
-    let total_size = response.content_length().unwrap_or(0);
-    
+    queue.download_finished(pack_id);
+    cancel.check()?;
+
+    // Check every entry before installing, a corrupted archive is dropped so the next attempt downloads it again
+    // The archive is hashed at the same time, both read it whole
+    emit_progress(
+        app,
+        DownloadProgress::new(pack_id, DownloadStage::Verifying, 0, 0),
+    );
+    let verified = {
+        let zip_path = zip_path.to_path_buf();
+        let cancel = cancel.clone();
+        let limits = crate::settings::Settings::load_or_default().extract_limits;
+        run_blocking(ErrorKind::Io, "Verification", move || {
+            super::integrity::verify_archive(&zip_path, &limits, &cancel)
+        })
+    };
+    let hashed = {
+        let zip_path = zip_path.to_path_buf();
+        run_blocking(ErrorKind::Io, "Hashing", move || {
+            super::integrity::sha256_file(&zip_path)
+        })
+    };
+    let (verified, archive_sha256) = tokio::join!(verified, hashed);
+    if let Err(e) = verified {
+        if e.is(ErrorKind::InvalidArchive) {
+            tracing::warn!("Archive failed verification: {}", e);
+            let _ = std::fs::remove_file(zip_path);
+            return Err(AppError {
+                message: format!("Downloaded archive is corrupted: {}", e),
+                ..e
+            });
+        }
+        return Err(e);
+    }
+    let archive_sha256 = archive_sha256?;
+    let previous_install = match crate::library::Library::load() {
+        Ok(library) => library.get(pack_id).cloned(),
+        Err(e) => {
+            tracing::warn!("Error loading library, the pack is installed as new: {}", e);
+            None
+        }
+    };
+    let previous_sha256 = previous_install
+        .as_ref()
+        .and_then(|pack| pack.archive_sha256.as_deref());
+    let archive_status = ArchiveStatus::compare(previous_sha256, &archive_sha256);
+    // Song folders of the previous install are replaced, any other folder is left alone
+    let owned_folders: Vec<std::path::PathBuf> = previous_install
+        .map(|pack| {
+            pack.song_folders
+                .iter()
+                .map(std::path::PathBuf::from)
+                .collect()
+        })
+        .unwrap_or_default();
+    tracing::info!("Archive SHA-256: {} ({:?})", archive_sha256, archive_status);
+
+    // Extract the archive on the blocking pool, reporting entries and bytes as they are written
+    emit_progress(
+        app,
+        DownloadProgress::new(pack_id, DownloadStage::Extracting, 0, 0),
+    );
+    let (extract_path, extract_report) = {
+        let app = app.clone();
+        let zip_path = zip_path.to_path_buf();
+        let cancel = cancel.clone();
+        run_blocking(ErrorKind::Io, "Extraction", move || {
+            let mut last_emit = std::time::Instant::now();
+            extract_archive(&zip_path, &cancel, &mut |done, total, bytes| {
+                if done == total || last_emit.elapsed() >= PROGRESS_INTERVAL {
+                    last_emit = std::time::Instant::now();
+                    let mut progress =
+                        DownloadProgress::new(pack_id, DownloadStage::Extracting, done, total);
+                    progress.bytes = Some(bytes);
+                    emit_progress(&app, progress);
+                }
+            })
+        })
+        .await?
+    };
+    cancel.check()?;
+
+    // Emit converting stage
+    emit_progress(
+        app,
+        DownloadProgress::new(pack_id, DownloadStage::Converting, 0, 0),
+    );
+
+    // Collections are named after the pack, fall back to the archive name
+    let pack_name = pack_name.unwrap_or_else(|| {
+        zip_path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+    });
+
+    // Process all .sm files found in the extracted directory on the blocking pool, reporting each song
+    let installed = {
+        let app = app.clone();
+        let extract_path = extract_path.clone();
+        let pack_name = pack_name.clone();
+        let cancel = cancel.clone();
+        run_blocking(ErrorKind::Conversion, "Conversion", move || {
+            process_sm_files(
+                &extract_path,
+                &pack_name,
+                pack_id,
+                &owned_folders,
+                &cancel,
+                &|done, total, title| {
+                    let mut progress =
+                        DownloadProgress::new(pack_id, DownloadStage::Converting, done, total);
+                    progress.current = (!title.is_empty()).then(|| title.to_string());
+                    emit_progress(&app, progress);
+                },
+            )
+        })
+        .await?
+    };
+    let mut report = installed.report;
+    report.archive_path = zip_path.to_string_lossy().to_string();
+    report.archive_status = archive_status;
+    report.skipped_entries = extract_report.skipped;
+    tracing::info!(
+        "{}/{} songs converted ({} charts, {} files), {} failures",
+        report.songs_converted,
+        report.songs_found,
+        report.charts_converted,
+        report.files_written,
+        report.failures.len()
+    );
+
+    // Record the pack and everything it produced in the library
+    let now = crate::library::now_timestamp();
+    let library_pack = crate::library::LibraryPack {
+        id: pack_id,
+        name: pack_name,
+        archive_path: zip_path.to_string_lossy().to_string(),
+        archive_sha256: Some(archive_sha256),
+        extract_path: extract_path.to_string_lossy().to_string(),
+        install_path: installed.install_path.to_string_lossy().to_string(),
+        song_folders: installed
+            .song_folders
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect(),
+        song_files: installed
+            .song_files
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect(),
+        osu_files: installed
+            .osu_files
+            .iter()
+            .map(|(path, hash)| crate::library::LibraryOsuFile {
+                path: path.to_string_lossy().to_string(),
+                hash: hash.clone(),
+            })
+            .collect(),
+        collection_name: installed.collection_name,
+        installed_at: now,
+        updated_at: now,
+    };
+    if let Err(e) = crate::library::Library::update(|library| library.upsert(library_pack)) {
+        tracing::warn!("Error updating library: {}", e);
+    }
+
+    Ok(report)
+}
+
+/// Runs a step that reads or writes whole archives on the blocking pool, keeping the pack span
+/// A panic of the step is reported as an error of `kind`
+async fn run_blocking<T: Send + 'static>(
+    kind: ErrorKind,
+    step: &'static str,
+    task: impl FnOnce() -> Result<T, AppError> + Send + 'static,
+) -> Result<T, AppError> {
+    let span = tracing::Span::current();
+    tokio::task::spawn_blocking(move || {
+        let _span = span.enter();
+        task()
+    })
+    .await
+    .map_err(|e| AppError::new(kind, format!("{} task failed: {}", step, e)))?
+}
+
+/// Returns where the archive of the given URL is saved in the downloads directory
+fn get_download_path(download_url: &str) -> Result<std::path::PathBuf, AppError> {
     // Extract filename from URL
     let filename = download_url
         .split('/')
-        .last()
+        .next_back()
         .unwrap_or("pack.zip")
         .split('?')
         .next()
         .unwrap_or("pack.zip");
-    
-    // Get downloads directory
-    let mut download_path = std::env::current_dir()
-        .map_err(|e| {
-            println!("[download_file] Error getting current directory: {}", e);
-            format!("Error getting current directory: {}", e)
-        })?;
-    download_path.push("downloads");
-    
-    // Create downloads directory if it doesn't exist
-    std::fs::create_dir_all(&download_path)
-        .map_err(|e| {
-            println!("[download_file] Error creating downloads directory: {}", e);
-            format!("Error creating downloads directory: {}", e)
-        })?;
-    
+
+    let mut download_path = get_downloads_dir()?;
     download_path.push(filename);
-    
-    let file_path_str = download_path.to_string_lossy().to_string();
-    println!("[download_file] Saving to: {}", file_path_str);
-    
-    // Create file for writing
-    let mut file = tokio::fs::File::create(&download_path)
-        .await
-        .map_err(|e| {
-            println!("[download_file] Error creating file: {}", e);
-            format!("Error creating file: {}", e)
-        })?;
-    
-    println!("[download_file] Starting streaming download...");
-    
-    use tokio::io::AsyncWriteExt;
-    
-    let mut total_bytes = 0u64;
-    let mut last_emit_time = std::time::Instant::now();
-    
-    // Stream chunks from response to file
-    loop {
-        match response.chunk().await {
-            Ok(Some(chunk)) => {
-                file.write_all(&chunk).await.map_err(|e| {
-                    println!("[download_file] Error writing chunk: {}", e);
-                    format!("Error writing chunk: {}", e)
-                })?;
-                
-                total_bytes += chunk.len() as u64;
-                
-                // Emit progress every 100KB or every 500ms
-                if total_bytes % 102_400 == 0 || last_emit_time.elapsed().as_millis() >= 500 {
-                    emit_progress(app, pack_id, total_bytes, total_size, "downloading");
-                    last_emit_time = std::time::Instant::now();
-                }
-            }
-            Ok(None) => {
-                // End of stream
-                break;
-            }
-            Err(e) => {
-                println!("[download_file] Error reading chunk: {}", e);
-                return Err(format!("Error reading chunk: {}", e));
-            }
+
+    Ok(download_path)
+}
+
+/// Returns the directory an archive is extracted to
+fn get_extract_path(zip_path: &std::path::Path) -> std::path::PathBuf {
+    zip_path.with_file_name(zip_path.file_stem().unwrap_or_default())
+}
+
+/// Downloads a file from the given URL to the given path
+/// An interrupted download is resumed from its .part file on the next attempt
+async fn download_file(
+    app: &tauri::AppHandle,
+    client: &reqwest::Client,
+    download_url: &str,
+    pack_id: u64,
+    download_path: &std::path::Path,
+    cancel: &CancelToken,
+) -> Result<(), AppError> {
+    tracing::debug!("Saving to: {}", download_path.to_string_lossy());
+
+    let total_bytes = super::resume::download_resumable(
+        client,
+        download_url,
+        download_path,
+        cancel,
+        |downloaded, total| {
+            emit_progress(
+                app,
+                DownloadProgress::new(pack_id, DownloadStage::Downloading, downloaded, total),
+            );
+        },
+    )
+    .await?;
+
+    let final_mb = total_bytes as f64 / 1_048_576.0;
+    tracing::info!("File saved successfully: {:.2} MB total", final_mb);
+
+    Ok(())
+}
+
+/// Which files of a pack were there before its download started
+struct ExistingFiles {
+    archive: bool,
+    extract_dir: bool,
+}
+
+impl ExistingFiles {
+    fn of(zip_path: &std::path::Path) -> Self {
+        ExistingFiles {
+            archive: zip_path.exists(),
+            extract_dir: get_extract_path(zip_path).exists(),
         }
     }
-    
-    // Emit final progress
-    emit_progress(app, pack_id, total_bytes, total_bytes, "downloading");
-    
-    file.sync_all().await.map_err(|e| {
-        println!("[download_file] Error syncing file: {}", e);
-        format!("Error syncing file: {}", e)
-    })?;
-    
-    let final_mb = total_bytes as f64 / 1_048_576.0;
-    println!("[download_file] File saved successfully: {:.2} MB total", final_mb);
-    
-    Ok(download_path)
 }
 
-/// Extracts a ZIP file to a directory
-fn extract_zip(zip_path: &std::path::Path) -> Result<std::path::PathBuf, String> {
-    println!("[extract_zip] Extracting zip file...");
-    
-    let extract_path = zip_path.parent().unwrap().join(
-        zip_path.file_stem().unwrap().to_string_lossy().to_string()
-    );
-    
-    std::fs::create_dir_all(&extract_path)
-        .map_err(|e| {
-            println!("[extract_zip] Error creating extract directory: {}", e);
-            format!("Error creating extract directory: {}", e)
-        })?;
-    
-    let file = std::fs::File::open(zip_path)
-        .map_err(|e| {
-            println!("[extract_zip] Error opening zip file: {}", e);
-            format!("Error opening zip file: {}", e)
-        })?;
-    
-    let mut archive = zip::ZipArchive::new(file)
-        .map_err(|e| {
-            println!("[extract_zip] Error reading zip archive: {}", e);
-            format!("Error reading zip archive: {}", e)
-        })?;
-    
-    archive.extract(&extract_path)
-        .map_err(|e| {
-            println!("[extract_zip] Error extracting zip: {}", e);
-            format!("Error extracting zip: {}", e)
-        })?;
-    
-    println!("[extract_zip] Zip extracted to: {}", extract_path.to_string_lossy());
-    
-    Ok(extract_path)
+/// Removes what a cancelled download created: the partial download, and the archive
+/// and extracted tree unless they were there before the download started
+fn clean_up_cancelled(zip_path: &std::path::Path, existing: &ExistingFiles) {
+    let mut files = vec![
+        super::resume::part_path(zip_path),
+        super::resume::sidecar_path(zip_path),
+    ];
+    if !existing.archive {
+        files.push(zip_path.to_path_buf());
+    }
+    for file in files.iter().filter(|f| f.exists()) {
+        if let Err(e) = std::fs::remove_file(file) {
+            tracing::warn!("Error removing {}: {}", file.display(), e);
+        }
+    }
+
+    let extract_path = get_extract_path(zip_path);
+    if !existing.extract_dir && extract_path.exists() {
+        if let Err(e) = std::fs::remove_dir_all(&extract_path) {
+            tracing::warn!("Error removing {}: {}", extract_path.display(), e);
+        }
+    }
 }
 
-/// Processes all .sm files found in the given directory
-fn process_sm_files(extract_path: &std::path::Path) -> Result<(), String> {
-    println!("[process_sm_files] Searching for .sm files...");
-    
-    let sm_files = find_sm_files(extract_path)?;
-    println!("[process_sm_files] Found {} .sm files", sm_files.len());
-    
-    // Collect all unique directories containing .sm files
+/// Returns the downloads directory set in the settings, creating it if it doesn't exist
+pub(crate) fn get_downloads_dir() -> Result<std::path::PathBuf, AppError> {
+    crate::settings::Settings::load_or_default().downloads_dir()
+}
+
+/// Extracts a pack archive (zip or 7z) to a directory next to it,
+/// within the limits configured in the settings
+/// Returns the extract directory and the entries that were skipped
+fn extract_archive(
+    archive_path: &std::path::Path,
+    cancel: &CancelToken,
+    on_progress: &mut dyn FnMut(u64, u64, u64),
+) -> Result<(std::path::PathBuf, super::extract::ExtractReport), AppError> {
+    tracing::info!("Extracting archive...");
+
+    let extract_path = get_extract_path(archive_path);
+    let settings = crate::settings::Settings::load_or_default();
+
+    let report = super::extract::extract_archive(
+        archive_path,
+        &extract_path,
+        &settings.extract_limits,
+        cancel,
+        on_progress,
+    )?;
+
+    Ok((extract_path, report))
+}
+
+/// Where the songs of a processed pack ended up
+struct InstalledSongs {
+    install_path: std::path::PathBuf,
+    song_folders: Vec<std::path::PathBuf>,
+    /// Files copied into the song folders, besides the generated .osu files
+    song_files: Vec<std::path::PathBuf>,
+    /// Generated .osu files with their MD5 hash
+    osu_files: Vec<(std::path::PathBuf, String)>,
+    collection_name: Option<String>,
+    /// Conversion counts and failures, without the archive path
+    report: InstallReport,
+}
+
+/// Processes all simfiles (.ssc, .sm, .dwi) found in the given directory
+/// Rated charts and audio are generated for the rates configured in the settings
+/// Simfiles are converted in parallel, progress is reported as
+/// (songs converted, song count, title of the song that just finished)
+/// `owned_folders` are the song folders of the previous install of the pack, they are replaced
+fn process_sm_files(
+    extract_path: &std::path::Path,
+    pack_name: &str,
+    pack_id: u64,
+    owned_folders: &[std::path::PathBuf],
+    cancel: &CancelToken,
+    on_progress: &(dyn Fn(u64, u64, &str) + Sync),
+) -> Result<InstalledSongs, AppError> {
+    tracing::debug!("Searching for simfiles...");
+
+    let sm_files = find_simfiles(extract_path)?;
+    tracing::info!("Found {} simfiles", sm_files.len());
+
+    // Collect all unique directories containing simfiles
     let mut song_dirs = std::collections::HashSet::new();
-    for sm_file in &sm_files {
+    for (sm_file, _) in &sm_files {
         if let Some(parent) = sm_file.parent() {
             song_dirs.insert(parent.to_path_buf());
         }
     }
-    
-    // Convert all .sm files
-    for sm_file in &sm_files {
-        println!("[process_sm_files] Processing .sm file: {}", sm_file.to_string_lossy());
-        convert_and_save_sm_file(sm_file);
+
+    let settings = crate::settings::Settings::load_or_default();
+
+    // Convert all simfiles
+    let converted = convert_all(&sm_files, &settings, cancel, on_progress)?;
+
+    let mut report = InstallReport {
+        songs_found: sm_files.len(),
+        ..Default::default()
+    };
+    let mut osu_paths = Vec::new();
+    for simfile in converted {
+        if !simfile.osu_paths.is_empty() {
+            report.songs_converted += 1;
+        }
+        report.charts_converted += simfile.charts;
+        report.files_written += simfile.osu_paths.len();
+        osu_paths.extend(simfile.osu_paths);
+
+        // Failures are listed relative to the pack
+        report
+            .failures
+            .extend(simfile.failures.into_iter().map(|mut failure| {
+                if let Ok(relative) = std::path::Path::new(&failure.file).strip_prefix(extract_path)
+                {
+                    failure.file = relative.to_string_lossy().to_string();
+                }
+                failure
+            }));
     }
-    
-    // Copy song directories to song_path if configured
-    let settings = crate::settings::Settings::load().unwrap_or_default();
-    if !settings.song_path.is_empty() {
-        copy_song_directories(&song_dirs, &settings.song_path)?;
+
+    let mut osu_files = Vec::new();
+    for osu_path in osu_paths {
+        let content = std::fs::read(&osu_path)
+            .map_err(|e| AppError::io(format!("Error reading {}", osu_path.display()), e))?;
+        osu_files.push((osu_path, crate::maps::collection::beatmap_hash(&content)));
     }
-    
-    Ok(())
+
+    // Without a song_path, songs stay in the extracted directory
+    if settings.song_path.is_empty() {
+        return Ok(InstalledSongs {
+            install_path: extract_path.to_path_buf(),
+            song_folders: song_dirs.into_iter().collect(),
+            song_files: Vec::new(),
+            osu_files,
+            collection_name: None,
+            report,
+        });
+    }
+
+    // Past this point the pack is installed, cancelling is no longer possible
+    cancel.check()?;
+
+    // Copy song directories to song_path
+    let copied = copy_song_directories(&song_dirs, &settings.song_path, pack_id, owned_folders)?;
+    report.song_folder_conflicts = copied.conflicts;
+
+    // Point the generated files to their copies
+    let osu_files: Vec<(std::path::PathBuf, String)> = osu_files
+        .into_iter()
+        .map(|(path, hash)| {
+            let copy = copied.dirs.iter().find_map(|(src, dst)| {
+                path.strip_prefix(src)
+                    .ok()
+                    .map(|relative| dst.join(relative))
+            });
+            (copy.unwrap_or(path), hash)
+        })
+        .collect();
+
+    // A broken collection.db should not fail an otherwise installed pack
+    let hashes: Vec<String> = osu_files.iter().map(|(_, hash)| hash.clone()).collect();
+    let collection_name = match update_collection(&settings.song_path, pack_name, hashes) {
+        Ok(true) => Some(pack_name.to_string()),
+        Ok(false) => None,
+        Err(e) => {
+            tracing::warn!("Error updating osu! collection: {}", e);
+            None
+        }
+    };
+
+    let song_files = copied
+        .files
+        .into_iter()
+        .filter(|file| !osu_files.iter().any(|(path, _)| path == file))
+        .collect();
+
+    Ok(InstalledSongs {
+        install_path: std::path::PathBuf::from(&settings.song_path),
+        song_folders: copied.dirs.into_iter().map(|(_, dst)| dst).collect(),
+        song_files,
+        osu_files,
+        collection_name,
+        report,
+    })
+}
+
+/// Converts simfiles on a dedicated thread pool sized by the settings
+/// Simfiles of the same song directory are converted by one thread, as they share their rated audio
+/// Returns one result per simfile, in the order of `sm_files`
+fn convert_all(
+    sm_files: &[(std::path::PathBuf, crate::maps::SimfileFormat)],
+    settings: &crate::settings::Settings,
+    cancel: &CancelToken,
+    on_progress: &(dyn Fn(u64, u64, &str) + Sync),
+) -> Result<Vec<ConvertedSimfile>, AppError> {
+    use rayon::prelude::*;
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(settings.conversion_threads)
+        .build()
+        .map_err(|e| {
+            AppError::new(
+                ErrorKind::Conversion,
+                format!("Error creating conversion thread pool: {}", e),
+            )
+        })?;
+
+    // find_simfiles lists the simfiles of a directory next to each other
+    let groups: Vec<&[(std::path::PathBuf, crate::maps::SimfileFormat)]> = sm_files
+        .chunk_by(|(a, _), (b, _)| a.parent() == b.parent())
+        .collect();
+
+    let total = sm_files.len() as u64;
+    let done = std::sync::atomic::AtomicU64::new(0);
+    let span = tracing::Span::current();
+    let results: Vec<Vec<ConvertedSimfile>> = pool.install(|| {
+        groups
+            .par_iter()
+            .map(|group| {
+                let _span = span.enter();
+                group
+                    .iter()
+                    .map(|(sm_file, format)| {
+                        if cancel.is_cancelled() {
+                            return ConvertedSimfile::default();
+                        }
+                        tracing::debug!(
+                            "Processing .{} file: {}",
+                            format.extension(),
+                            sm_file.to_string_lossy()
+                        );
+                        let result = convert_and_save_sm_file(sm_file, *format, settings);
+
+                        let done = done.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+                        on_progress(done, total, &song_title(sm_file));
+                        result
+                    })
+                    .collect()
+            })
+            .collect()
+    });
+
+    // Simfiles skipped because of a cancellation are not failures
+    cancel.check()?;
+
+    Ok(results.into_iter().flatten().collect())
+}
+
+/// Adds (or replaces) an osu! collection named after the pack with all generated .osu files
+/// Returns whether a collection was written
+fn update_collection(
+    song_path: &str,
+    pack_name: &str,
+    beatmap_hashes: Vec<String>,
+) -> Result<bool, AppError> {
+    use crate::maps::collection::{collection_db_path, Collection, CollectionDb};
+
+    let Some(db_path) = collection_db_path(song_path) else {
+        tracing::info!(
+            "No osu! installation found next to {}, skipping collection",
+            song_path
+        );
+        return Ok(false);
+    };
+
+    let map_count = beatmap_hashes.len();
+    let mut db = CollectionDb::load(&db_path)?;
+    db.upsert(Collection {
+        name: pack_name.to_string(),
+        beatmap_hashes,
+    });
+    db.save(&db_path)?;
+
+    tracing::info!(
+        "Saved collection {} ({} maps) to {}",
+        pack_name,
+        map_count,
+        db_path.display()
+    );
+
+    Ok(true)
+}
+
+/// Song directories copied to the song path
+struct CopiedSongs {
+    /// (source, destination) pair of every copied directory
+    dirs: Vec<(std::path::PathBuf, std::path::PathBuf)>,
+    /// Every file written
+    files: Vec<std::path::PathBuf>,
+    conflicts: Vec<SongFolderConflict>,
 }
 
 /// Copies song directories to the configured song path
+/// A folder the pack does not own is never replaced: the song is installed as `Name (pack id)`,
+/// or left out if that name is taken too
 fn copy_song_directories(
     song_dirs: &std::collections::HashSet<std::path::PathBuf>,
     song_path: &str,
-) -> Result<(), String> {
+    pack_id: u64,
+    owned_folders: &[std::path::PathBuf],
+) -> Result<CopiedSongs, AppError> {
     let target_path = std::path::Path::new(song_path);
-    
+
     // Create target directory if it doesn't exist
     std::fs::create_dir_all(target_path)
-        .map_err(|e| format!("Error creating song path directory: {}", e))?;
-    
-    println!("[copy_song_directories] Copying {} song directories to: {}", song_dirs.len(), song_path);
-    
+        .map_err(|e| AppError::io("Error creating song path directory", e))?;
+
+    tracing::info!(
+        "Copying {} song directories to: {}",
+        song_dirs.len(),
+        song_path
+    );
+
+    let mut copied_dirs: Vec<(std::path::PathBuf, std::path::PathBuf)> = Vec::new();
+    let mut copied_files = Vec::new();
+    let mut conflicts = Vec::new();
     for song_dir in song_dirs {
-        let dir_name = song_dir.file_name()
+        let dir_name = song_dir
+            .file_name()
             .and_then(|n| n.to_str())
-            .ok_or_else(|| "Invalid directory name".to_string())?;
-        
+            .ok_or_else(|| {
+                AppError::new(ErrorKind::Io, "Invalid directory name")
+                    .with_details(song_dir.display().to_string())
+            })?;
+
+        // Free unless another folder of this pack or a folder the pack doesn't own is there
+        let is_free = |dir: &std::path::Path| {
+            !copied_dirs.iter().any(|(_, dst)| dst == dir)
+                && (!dir.exists() || owned_folders.iter().any(|owned| owned == dir))
+        };
         let target_dir = target_path.join(dir_name);
-        
-        // Remove target directory if it exists
+        let target_dir = if is_free(&target_dir) {
+            target_dir
+        } else {
+            let renamed = target_path.join(format!("{} ({})", dir_name, pack_id));
+            let installed = is_free(&renamed).then_some(renamed);
+            tracing::warn!(
+                "{} already exists, installing the song {}",
+                target_dir.display(),
+                installed
+                    .as_ref()
+                    .map_or("nowhere".to_string(), |dir| format!("as {}", dir.display()))
+            );
+            conflicts.push(SongFolderConflict {
+                name: dir_name.to_string(),
+                installed_as: installed
+                    .as_ref()
+                    .and_then(|dir| dir.file_name())
+                    .map(|name| name.to_string_lossy().to_string()),
+            });
+            match installed {
+                Some(dir) => dir,
+                None => continue,
+            }
+        };
+
+        // Replace the copy left by the previous install of the pack
         if target_dir.exists() {
             std::fs::remove_dir_all(&target_dir)
-                .map_err(|e| format!("Error removing existing directory: {}", e))?;
+                .map_err(|e| AppError::io("Error removing existing directory", e))?;
         }
-        
+
         // Copy directory
-        copy_dir_all(song_dir, &target_dir)
-            .map_err(|e| format!("Error copying directory {}: {}", song_dir.display(), e))?;
-        
-        println!("[copy_song_directories] Copied {} to {}", song_dir.display(), target_dir.display());
+        copy_dir_all(song_dir, &target_dir, &mut copied_files).map_err(|e| {
+            AppError::io(format!("Error copying directory {}", song_dir.display()), e)
+        })?;
+
+        tracing::debug!("Copied {} to {}", song_dir.display(), target_dir.display());
+        copied_dirs.push((song_dir.clone(), target_dir));
     }
-    
-    Ok(())
+
+    Ok(CopiedSongs {
+        dirs: copied_dirs,
+        files: copied_files,
+        conflicts,
+    })
 }
 
-/// Recursively copies a directory
-fn copy_dir_all(src: &std::path::Path, dst: &std::path::Path) -> Result<(), String> {
-    std::fs::create_dir_all(dst)
-        .map_err(|e| format!("Error creating destination directory: {}", e))?;
-    
-    for entry in std::fs::read_dir(src)
-        .map_err(|e| format!("Error reading source directory: {}", e))? {
-        let entry = entry.map_err(|e| format!("Error reading directory entry: {}", e))?;
+/// Recursively copies a directory, adding every file written to `copied`
+fn copy_dir_all(
+    src: &std::path::Path,
+    dst: &std::path::Path,
+    copied: &mut Vec<std::path::PathBuf>,
+) -> std::io::Result<()> {
+    std::fs::create_dir_all(dst)?;
+
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
         let path = entry.path();
         let file_name = entry.file_name();
         let dst_path = dst.join(&file_name);
-        
+
         if path.is_dir() {
-            copy_dir_all(&path, &dst_path)?;
+            copy_dir_all(&path, &dst_path, copied)?;
         } else {
-            std::fs::copy(&path, &dst_path)
-                .map_err(|e| format!("Error copying file: {}", e))?;
+            std::fs::copy(&path, &dst_path)?;
+            copied.push(dst_path);
         }
     }
-    
+
     Ok(())
 }
 
-/// Converts a single .sm file to .osu format and saves the results
-fn convert_and_save_sm_file(sm_file: &std::path::Path) {
+/// What came out of converting one simfile
+#[derive(Default)]
+struct ConvertedSimfile {
+    /// .osu files written, rated charts included
+    osu_paths: Vec<std::path::PathBuf>,
+    /// Charts converted at 1.0x
+    charts: usize,
+    failures: Vec<ConversionFailure>,
+}
+
+impl ConvertedSimfile {
+    fn failed(sm_file: &std::path::Path, reason: ConversionFailureReason, message: String) -> Self {
+        tracing::warn!("{}: {}", sm_file.display(), message);
+        ConvertedSimfile {
+            failures: vec![ConversionFailure {
+                file: sm_file.to_string_lossy().to_string(),
+                reason,
+                message,
+            }],
+            ..Default::default()
+        }
+    }
+}
+
+/// Converts a single simfile to .osu format and saves the results
+/// Charts that fail to save and rates whose audio fails to render are reported without failing the others
+fn convert_and_save_sm_file(
+    sm_file: &std::path::Path,
+    format: crate::maps::SimfileFormat,
+    settings: &crate::settings::Settings,
+) -> ConvertedSimfile {
     // Read file content
-    let file_content = match std::fs::read(sm_file) {
+    let mut file_content = match std::fs::read(sm_file) {
         Ok(content) => content,
         Err(e) => {
-            println!("[convert_and_save_sm_file] Error reading .sm file {}: {}", sm_file.display(), e);
-            return;
+            let message = format!("Error reading .{} file: {}", format.extension(), e);
+            return ConvertedSimfile::failed(sm_file, ConversionFailureReason::Io, message);
         }
     };
-    
-    // Convert .sm to .osu
-    let osu_files = match crate::maps::from_sm_to_osu(file_content) {
+
+    // DWI files often omit #FILE and rely on the audio sitting next to them
+    if format == crate::maps::SimfileFormat::Dwi
+        && !String::from_utf8_lossy(&file_content)
+            .to_uppercase()
+            .contains("#FILE:")
+    {
+        if let Some(audio_file) = sm_file.parent().and_then(find_audio_file) {
+            file_content.extend_from_slice(format!("\n#FILE:{};\n", audio_file).as_bytes());
+        }
+    }
+
+    // Convert to .osu
+    let osu_files = match crate::maps::from_simfile_to_osu(format, file_content, settings) {
         Ok(files) => files,
         Err(e) => {
-            println!("[convert_and_save_sm_file] Error converting .sm to .osu: {}", e);
-            return;
+            let reason = match e {
+                crate::maps::ConversionError::Decode(_) => ConversionFailureReason::Decode,
+                crate::maps::ConversionError::Encode(_) => ConversionFailureReason::Encode,
+            };
+            let message = format!("Error converting .{} to .osu: {}", format.extension(), e);
+            return ConvertedSimfile::failed(sm_file, reason, message);
         }
     };
-    
-    println!("[convert_and_save_sm_file] Successfully converted to {} .osu file(s)", osu_files.len());
-    
+
+    tracing::debug!("Successfully converted to {} .osu file(s)", osu_files.len());
+
     // Get the base name and parent directory of the .sm file
     let Some(sm_dir) = sm_file.parent() else {
-        println!("[convert_and_save_sm_file] Could not get parent directory of {}", sm_file.display());
-        return;
+        let message = format!("Could not get parent directory of {}", sm_file.display());
+        return ConvertedSimfile::failed(sm_file, ConversionFailureReason::Io, message);
     };
-    
-    let base_name = sm_file.file_stem()
+    let mut converted = ConvertedSimfile::default();
+
+    let base_name = sm_file
+        .file_stem()
         .and_then(|s| s.to_str())
         .unwrap_or("output");
-    
+
+    // Render the audio of each rate once, rated charts are dropped if it fails
+    let mut failed_rates = Vec::new();
+    for osu_file in osu_files.iter().filter(|f| f.rate != 1.0) {
+        if failed_rates.contains(&osu_file.rate) {
+            continue;
+        }
+
+        let rated_audio = sm_dir.join(crate::maps::rates::rated_audio_file_name(
+            &osu_file.audio_file,
+            osu_file.rate,
+        ));
+        if rated_audio.exists() {
+            continue;
+        }
+
+        let source_audio = sm_dir.join(&osu_file.audio_file);
+        if let Err(e) = crate::maps::rates::generate_rated_audio(
+            &settings.ffmpeg_path,
+            &source_audio,
+            &rated_audio,
+            osu_file.rate,
+        ) {
+            tracing::warn!("Error generating rated audio: {}", e);
+            failed_rates.push(osu_file.rate);
+            converted.failures.push(ConversionFailure {
+                file: sm_file.to_string_lossy().to_string(),
+                reason: ConversionFailureReason::Audio,
+                message: format!(
+                    "Error generating {} audio: {}",
+                    crate::maps::rates::format_rate(osu_file.rate),
+                    e
+                ),
+            });
+        }
+    }
+
     // Save each .osu file next to the .sm file
-    for (difficulty, osu_bytes) in osu_files {
-        let osu_filename = format!("{} - {}.osu", base_name, difficulty);
+    let mut used_names = std::collections::HashSet::new();
+    for osu_file in osu_files
+        .into_iter()
+        .filter(|f| !failed_rates.contains(&f.rate))
+    {
+        let stem = format!(
+            "{} - {}",
+            base_name,
+            sanitize_file_name(&osu_file.difficulty_name)
+        );
+        let osu_filename = unique_file_name(&stem, "osu", &mut used_names);
         let osu_path = sm_dir.join(&osu_filename);
-        
-        match std::fs::write(&osu_path, &osu_bytes) {
+
+        match std::fs::write(&osu_path, &osu_file.content) {
             Ok(_) => {
-                println!("[convert_and_save_sm_file] Saved .osu file: {}", osu_path.display());
+                tracing::debug!("Saved .osu file: {}", osu_path.display());
+                if osu_file.rate == 1.0 {
+                    converted.charts += 1;
+                }
+                converted.osu_paths.push(osu_path);
             }
             Err(e) => {
-                println!("[convert_and_save_sm_file] Error saving .osu file {}: {}", osu_path.display(), e);
+                tracing::warn!("Error saving .osu file {}: {}", osu_path.display(), e);
+                converted.failures.push(ConversionFailure {
+                    file: sm_file.to_string_lossy().to_string(),
+                    reason: ConversionFailureReason::Io,
+                    message: format!("Error saving {}: {}", osu_filename, e),
+                });
             }
         }
     }
+
+    converted
+}
+
+/// Returns the #TITLE of a simfile, falling back to the name of its song directory
+fn song_title(sm_file: &std::path::Path) -> String {
+    std::fs::read(sm_file)
+        .ok()
+        .and_then(|content| crate::maps::simfile_title(&String::from_utf8_lossy(&content)))
+        .or_else(|| {
+            sm_file
+                .parent()
+                .and_then(|dir| dir.file_name())
+                .map(|name| name.to_string_lossy().to_string())
+        })
+        .unwrap_or_default()
+}
+
+/// Replaces characters that are not allowed in file names on Windows
+fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim_end_matches(['.', ' '])
+        .to_string()
+}
+
+/// Returns `stem.extension`, numbered `stem (2).extension` and so on when the name is already used
+/// Names are compared ignoring case, like Windows and macOS file systems do
+fn unique_file_name(
+    stem: &str,
+    extension: &str,
+    used: &mut std::collections::HashSet<String>,
+) -> String {
+    let mut name = format!("{}.{}", stem, extension);
+    let mut number = 2;
+    while !used.insert(name.to_lowercase()) {
+        name = format!("{} ({}).{}", stem, number, extension);
+        number += 1;
+    }
+    name
+}
+
+/// Returns the name of the first audio file found in a song directory
+fn find_audio_file(dir: &std::path::Path) -> Option<String> {
+    const AUDIO_EXTENSIONS: &[&str] = &["mp3", "ogg", "wav"];
+
+    let mut audio_files: Vec<String> = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .and_then(|s| s.to_str())
+                .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        })
+        .filter_map(|path| path.file_name().and_then(|n| n.to_str()).map(String::from))
+        .collect();
+
+    audio_files.sort();
+    audio_files.into_iter().next()
 }
 
-fn find_sm_files(dir: &std::path::Path) -> Result<Vec<std::path::PathBuf>, String> {
+/// Finds all simfiles in the given directory
+/// When a song directory contains several formats, only the preferred one is kept (.ssc > .sm > .dwi)
+fn find_simfiles(
+    dir: &std::path::Path,
+) -> Result<Vec<(std::path::PathBuf, crate::maps::SimfileFormat)>, AppError> {
     let mut sm_files = Vec::new();
-    
-    fn walk_dir(dir: &std::path::Path, sm_files: &mut Vec<std::path::PathBuf>) -> Result<(), String> {
-        let entries = std::fs::read_dir(dir)
-            .map_err(|e| format!("Error reading directory: {}", e))?;
-        
+
+    fn walk_dir(
+        dir: &std::path::Path,
+        sm_files: &mut Vec<(std::path::PathBuf, crate::maps::SimfileFormat)>,
+    ) -> Result<(), AppError> {
+        let entries =
+            std::fs::read_dir(dir).map_err(|e| AppError::io("Error reading directory", e))?;
+
+        let mut dir_files = Vec::new();
+
         for entry in entries {
-            let entry = entry.map_err(|e| format!("Error reading directory entry: {}", e))?;
+            let entry = entry.map_err(|e| AppError::io("Error reading directory entry", e))?;
             let path = entry.path();
-            
+
             if path.is_dir() {
                 walk_dir(&path, sm_files)?;
-            } else if path.extension().and_then(|s| s.to_str()) == Some("sm") {
-                sm_files.push(path);
+            } else if let Some(format) = crate::maps::SimfileFormat::from_path(&path) {
+                dir_files.push((path, format));
             }
         }
-        
+
+        // Keep only the preferred format of this directory
+        if let Some(best) = dir_files.iter().map(|(_, format)| *format).min() {
+            dir_files.retain(|(_, format)| *format == best);
+            dir_files.sort();
+            sm_files.extend(dir_files);
+        }
+
         Ok(())
     }
-    
+
     walk_dir(dir, &mut sm_files)?;
     Ok(sm_files)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maps::SimfileFormat;
+    use crate::test_utils::{TempDir, SIMPLE_SM};
+
+    #[test]
+    fn cancelling_a_re_download_keeps_the_installed_pack() {
+        let dir = TempDir::new("cancel-cleanup");
+        let zip_path = dir.join("Pack.zip");
+        let installed_song = dir.join("Pack").join("Song").join("chart.sm");
+        std::fs::create_dir_all(installed_song.parent().unwrap()).unwrap();
+        std::fs::write(&installed_song, SIMPLE_SM).unwrap();
+
+        let existing = ExistingFiles::of(&zip_path);
+        std::fs::write(crate::packs::resume::part_path(&zip_path), b"part").unwrap();
+        std::fs::write(crate::packs::resume::sidecar_path(&zip_path), b"{}").unwrap();
+        std::fs::write(&zip_path, b"zip").unwrap();
+        clean_up_cancelled(&zip_path, &existing);
+
+        assert!(installed_song.exists());
+        assert!(!zip_path.exists());
+        assert!(!crate::packs::resume::part_path(&zip_path).exists());
+        assert!(!crate::packs::resume::sidecar_path(&zip_path).exists());
+
+        // Nothing was installed before this one, the extracted tree goes too
+        std::fs::remove_dir_all(dir.join("Pack")).unwrap();
+        let existing = ExistingFiles::of(&zip_path);
+        std::fs::create_dir_all(installed_song.parent().unwrap()).unwrap();
+        clean_up_cancelled(&zip_path, &existing);
+        assert!(!dir.join("Pack").exists());
+    }
+
+    #[test]
+    fn parallel_conversion_keeps_the_simfile_order() {
+        let dir = TempDir::new("convert");
+
+        let mut sm_files = Vec::new();
+        for index in 0..8 {
+            let song_dir = dir.join(format!("Song {}", index));
+            std::fs::create_dir_all(&song_dir).unwrap();
+            let sm_file = song_dir.join("chart.sm");
+            // Every third simfile has no chart
+            let content = if index % 3 == 0 {
+                "#TITLE:Broken;"
+            } else {
+                SIMPLE_SM
+            };
+            std::fs::write(&sm_file, content).unwrap();
+            sm_files.push((sm_file, SimfileFormat::Sm));
+        }
+
+        let settings = crate::settings::Settings {
+            conversion_threads: 4,
+            ..Default::default()
+        };
+        let done = std::sync::Mutex::new(Vec::new());
+        let results = convert_all(
+            &sm_files,
+            &settings,
+            &CancelToken::default(),
+            &|count, total, _| {
+                done.lock().unwrap().push((count, total));
+            },
+        )
+        .unwrap();
+
+        let converted: Vec<bool> = results
+            .iter()
+            .map(|simfile| simfile.failures.is_empty())
+            .collect();
+        assert_eq!(
+            converted,
+            (0..8).map(|index| index % 3 != 0).collect::<Vec<_>>()
+        );
+        assert!(results[1].osu_paths[0].starts_with(dir.join("Song 1")));
+        assert_eq!(results[1].charts, 1);
+        assert_eq!(
+            results[3].failures[0].reason,
+            ConversionFailureReason::Decode
+        );
+
+        let mut done = done.into_inner().unwrap();
+        done.sort();
+        assert_eq!(done, (1..=8).map(|count| (count, 8)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn song_folders_never_replace_folders_of_others() {
+        let dir = TempDir::new("copy-conflict");
+        let pack_song = dir.join("Pack").join("Song");
+        std::fs::create_dir_all(&pack_song).unwrap();
+        std::fs::write(pack_song.join("chart.sm"), SIMPLE_SM).unwrap();
+        let songs = dir.join("Songs");
+        std::fs::create_dir_all(songs.join("Song")).unwrap();
+        std::fs::write(songs.join("Song").join("mine.osu"), b"user file").unwrap();
+
+        let song_dirs = std::collections::HashSet::from([pack_song]);
+        let song_path = songs.to_string_lossy().to_string();
+        let copied = copy_song_directories(&song_dirs, &song_path, 7, &[]).unwrap();
+
+        assert_eq!(
+            std::fs::read(songs.join("Song").join("mine.osu")).unwrap(),
+            b"user file"
+        );
+        assert!(songs.join("Song (7)").join("chart.sm").exists());
+        assert_eq!(copied.dirs[0].1, songs.join("Song (7)"));
+        assert_eq!(
+            copied.conflicts,
+            vec![SongFolderConflict {
+                name: "Song".to_string(),
+                installed_as: Some("Song (7)".to_string()),
+            }]
+        );
+
+        // A re-install replaces its own copy
+        let owned = vec![songs.join("Song (7)")];
+        let copied = copy_song_directories(&song_dirs, &song_path, 7, &owned).unwrap();
+        assert_eq!(copied.dirs[0].1, songs.join("Song (7)"));
+
+        // Without the previous install on record, both names are taken and the song is left out
+        let copied = copy_song_directories(&song_dirs, &song_path, 7, &[]).unwrap();
+        assert!(copied.dirs.is_empty());
+        assert_eq!(copied.conflicts[0].installed_as, None);
+        assert!(songs.join("Song (7)").join("chart.sm").exists());
+    }
+
+    #[test]
+    fn difficulty_names_sanitised_alike_get_distinct_files() {
+        let mut used = std::collections::HashSet::new();
+        let names: Vec<String> = ["a:b", "a?b", "A*B", "c"]
+            .iter()
+            .map(|difficulty| {
+                let stem = format!("chart - {}", sanitize_file_name(difficulty));
+                unique_file_name(&stem, "osu", &mut used)
+            })
+            .collect();
+
+        assert_eq!(
+            names,
+            vec![
+                "chart - a_b.osu",
+                "chart - a_b (2).osu",
+                "chart - A_B (3).osu",
+                "chart - c.osu"
+            ]
+        );
+    }
+}
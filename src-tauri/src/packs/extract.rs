@@ -0,0 +1,867 @@
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use super::jobs::CancelToken;
+use crate::error::{AppError, ErrorKind};
+
+/// Limits protecting against archive bombs, configurable in the settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExtractLimits {
+    /// Total uncompressed size of the archive, in MB
+    pub max_total_size_mb: u64,
+    pub max_entries: usize,
+    /// Largest uncompressed/compressed size ratio of a single entry
+    pub max_compression_ratio: u64,
+}
+
+impl ExtractLimits {
+    fn max_total_size(&self) -> u64 {
+        self.max_total_size_mb.saturating_mul(1024 * 1024)
+    }
+}
+
+impl Default for ExtractLimits {
+    fn default() -> Self {
+        ExtractLimits {
+            max_total_size_mb: 16 * 1024,
+            max_entries: 100_000,
+            max_compression_ratio: 200,
+        }
+    }
+}
+
+/// Entries smaller than this are not checked against the compression ratio,
+/// tiny files of repeated bytes compress far beyond any sane limit
+const RATIO_CHECK_MIN_SIZE: u64 = 1024 * 1024;
+
+/// An archive entry that was not extracted
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SkippedEntry {
+    pub name: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ExtractReport {
+    pub extracted: usize,
+    pub skipped: Vec<SkippedEntry>,
+}
+
+/// Archive formats packs are distributed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    SevenZip,
+    Rar,
+}
+
+impl ArchiveFormat {
+    /// Detects the format from the first bytes of the file, the URL suffix is not reliable
+    pub fn detect(path: &Path) -> Result<ArchiveFormat, AppError> {
+        let mut magic = [0u8; 8];
+        let mut file =
+            std::fs::File::open(path).map_err(|e| AppError::io("Error opening archive", e))?;
+        let read = file
+            .read(&mut magic)
+            .map_err(|e| AppError::io("Error reading archive", e))?;
+
+        Self::from_magic(&magic[..read])
+            .ok_or_else(|| invalid_archive(format!("Unknown archive format: {}", path.display())))
+    }
+
+    fn from_magic(bytes: &[u8]) -> Option<ArchiveFormat> {
+        if bytes.starts_with(b"PK\x03\x04") || bytes.starts_with(b"PK\x05\x06") {
+            Some(ArchiveFormat::Zip)
+        } else if bytes.starts_with(b"7z\xBC\xAF\x27\x1C") {
+            Some(ArchiveFormat::SevenZip)
+        } else if bytes.starts_with(b"Rar!\x1A\x07") {
+            Some(ArchiveFormat::Rar)
+        } else {
+            None
+        }
+    }
+}
+
+/// Extracts a pack archive to `extract_path`, whatever its format
+/// Entries with hostile names and symlinks are skipped, archives over the limits are rejected
+/// The archive is extracted next to `extract_path` and only replaces it once complete,
+/// a failed or cancelled extraction leaves nothing behind
+/// Progress is reported as (entries processed, entry count, bytes written)
+pub fn extract_archive(
+    archive_path: &Path,
+    extract_path: &Path,
+    limits: &ExtractLimits,
+    cancel: &CancelToken,
+    on_progress: &mut dyn FnMut(u64, u64, u64),
+) -> Result<ExtractReport, AppError> {
+    let format = ArchiveFormat::detect(archive_path)?;
+    tracing::debug!("Detected {:?} archive", format);
+
+    let staging_path = staging_path(extract_path);
+    // Left over by a crash
+    remove_dir(&staging_path);
+
+    let extracted = match format {
+        ArchiveFormat::Zip => extract_zip(archive_path, &staging_path, limits, cancel, on_progress),
+        ArchiveFormat::SevenZip => {
+            extract_7z(archive_path, &staging_path, limits, cancel, on_progress)
+        }
+        ArchiveFormat::Rar => Err(invalid_archive(
+            "RAR archives are not supported: no pure-Rust RAR decoder is available, extract the pack manually",
+        )),
+    };
+    let report = match extracted.and_then(|report| {
+        replace_dir(&staging_path, extract_path)?;
+        Ok(report)
+    }) {
+        Ok(report) => report,
+        Err(e) => {
+            remove_dir(&staging_path);
+            return Err(e);
+        }
+    };
+
+    for skipped in &report.skipped {
+        tracing::warn!("Skipped {}: {}", skipped.name, skipped.reason);
+    }
+    tracing::info!(
+        "Archive extracted to: {} ({} files, {} skipped)",
+        extract_path.to_string_lossy(),
+        report.extracted,
+        report.skipped.len()
+    );
+
+    Ok(report)
+}
+
+/// Opens a zip archive, rejecting it if its declared sizes are over the limits
+pub(super) fn open_zip(
+    zip_path: &Path,
+    limits: &ExtractLimits,
+) -> Result<zip::ZipArchive<std::fs::File>, AppError> {
+    let file = std::fs::File::open(zip_path).map_err(|e| {
+        tracing::error!("Error opening zip file: {}", e);
+        AppError::io("Error opening zip file", e)
+    })?;
+
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| {
+        tracing::error!("Error reading zip archive: {}", e);
+        invalid_archive(format!("Error reading zip archive: {}", e))
+    })?;
+
+    // Check the declared sizes before writing anything
+    let mut declared = Vec::new();
+    for index in 0..archive.len() {
+        let entry = archive
+            .by_index_raw(index)
+            .map_err(|e| invalid_archive(format!("Error reading zip entry: {}", e)))?;
+        declared.push((
+            entry.name().to_string(),
+            entry.size(),
+            entry.compressed_size(),
+        ));
+    }
+    check_declared_sizes(&declared, limits)?;
+
+    Ok(archive)
+}
+
+fn extract_zip(
+    zip_path: &Path,
+    extract_path: &Path,
+    limits: &ExtractLimits,
+    cancel: &CancelToken,
+    on_progress: &mut dyn FnMut(u64, u64, u64),
+) -> Result<ExtractReport, AppError> {
+    let mut archive = open_zip(zip_path, limits)?;
+
+    let budget = Budget::new(limits, cancel, None);
+    let mut writer = EntryWriter::new(extract_path, budget, archive.len(), on_progress)?;
+    for index in 0..archive.len() {
+        cancel.check()?;
+
+        let mut entry = archive.by_index(index).map_err(|e| {
+            tracing::error!("Error reading zip entry: {}", e);
+            invalid_archive(format!("Error reading zip entry: {}", e))
+        })?;
+        let name = entry.name().to_string();
+        let (is_dir, is_symlink) = (entry.is_dir(), entry.is_symlink());
+        let compressed_size = entry.compressed_size();
+
+        writer.write(&name, is_dir, is_symlink, compressed_size, &mut entry)?;
+    }
+
+    Ok(writer.report)
+}
+
+/// Opens a 7z archive, rejecting it if its declared sizes are over the limits
+/// Returns the archive with its size on disk, the unpacked bytes are checked against it as a whole
+pub(super) fn open_7z(
+    archive_path: &Path,
+    limits: &ExtractLimits,
+) -> Result<(sevenz_rust::SevenZReader<std::fs::File>, u64), AppError> {
+    let reader = sevenz_rust::SevenZReader::open(archive_path, sevenz_rust::Password::empty())
+        .map_err(|e| {
+            tracing::error!("Error reading 7z archive: {}", e);
+            invalid_archive(format!("Error reading 7z archive: {}", e))
+        })?;
+
+    // Solid archives only know the packed size of whole blocks, so the ratio is checked per archive
+    let files = &reader.archive().files;
+    let declared: Vec<(String, u64, u64)> = files
+        .iter()
+        .map(|entry| {
+            (
+                entry.name().to_string(),
+                entry.size(),
+                entry.compressed_size,
+            )
+        })
+        .collect();
+    check_declared_sizes(&declared, limits)?;
+    let archive_size = std::fs::metadata(archive_path)
+        .map(|m| m.len())
+        .unwrap_or(0);
+    let total_size: u64 = declared.iter().map(|(_, size, _)| size).sum();
+    if exceeds_ratio(
+        total_size,
+        archive_size.max(1),
+        limits.max_compression_ratio,
+    ) {
+        return Err(invalid_archive(format!(
+            "Archive has a compression ratio over {}",
+            limits.max_compression_ratio
+        )));
+    }
+
+    Ok((reader, archive_size))
+}
+
+fn extract_7z(
+    archive_path: &Path,
+    extract_path: &Path,
+    limits: &ExtractLimits,
+    cancel: &CancelToken,
+    on_progress: &mut dyn FnMut(u64, u64, u64),
+) -> Result<ExtractReport, AppError> {
+    let (mut reader, archive_size) = open_7z(archive_path, limits)?;
+    let entry_count = reader.archive().files.len();
+
+    let budget = Budget::new(limits, cancel, Some(archive_size));
+    let mut writer = EntryWriter::new(extract_path, budget, entry_count, on_progress)?;
+    let mut error = None;
+    reader
+        .for_each_entries(|entry, entry_reader| {
+            let result = cancel.check().and_then(|_| {
+                // The packed size of an entry is unknown, only the whole archive is checked
+                writer.write(
+                    entry.name(),
+                    entry.is_directory(),
+                    is_7z_symlink(entry),
+                    0,
+                    entry_reader,
+                )
+            });
+            match result {
+                Ok(()) => {
+                    // Entries of a solid block are read in sequence, skipped ones must be consumed
+                    std::io::copy(entry_reader, &mut std::io::sink())?;
+                    Ok(true)
+                }
+                Err(e) => {
+                    error = Some(e);
+                    Ok(false)
+                }
+            }
+        })
+        .map_err(|e| {
+            tracing::error!("Error extracting 7z: {}", e);
+            invalid_archive(format!("Error extracting 7z: {}", e))
+        })?;
+
+    match error {
+        Some(e) => Err(e),
+        None => Ok(writer.report),
+    }
+}
+
+/// Directory an archive is extracted to before it replaces `extract_path`
+fn staging_path(extract_path: &Path) -> PathBuf {
+    let mut name = extract_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".partial");
+    extract_path.with_file_name(name)
+}
+
+/// Moves a complete extraction in place of the previous one
+fn replace_dir(staging_path: &Path, extract_path: &Path) -> Result<(), AppError> {
+    if extract_path.exists() {
+        std::fs::remove_dir_all(extract_path)
+            .map_err(|e| AppError::io(format!("Error removing {}", extract_path.display()), e))?;
+    }
+    std::fs::rename(staging_path, extract_path)
+        .map_err(|e| AppError::io(format!("Error moving {}", staging_path.display()), e))
+}
+
+fn remove_dir(path: &Path) {
+    if path.exists() {
+        if let Err(e) = std::fs::remove_dir_all(path) {
+            tracing::warn!("Error removing {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// 7z stores unix modes in the high bits of the Windows attributes
+fn is_7z_symlink(entry: &sevenz_rust::SevenZArchiveEntry) -> bool {
+    const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+    const FILE_ATTRIBUTE_UNIX_EXTENSION: u32 = 0x8000;
+    const S_IFMT: u32 = 0o170000;
+    const S_IFLNK: u32 = 0o120000;
+
+    let attributes = entry.windows_attributes();
+    entry.has_windows_attributes
+        && (attributes & FILE_ATTRIBUTE_REPARSE_POINT != 0
+            || (attributes & FILE_ATTRIBUTE_UNIX_EXTENSION != 0
+                && (attributes >> 16) & S_IFMT == S_IFLNK))
+}
+
+/// Rejects archives whose declared entry count, total size or compression ratio is over the limits
+/// Entries are (name, uncompressed size, compressed size), a compressed size of 0 is unknown
+fn check_declared_sizes(
+    entries: &[(String, u64, u64)],
+    limits: &ExtractLimits,
+) -> Result<(), AppError> {
+    if entries.len() > limits.max_entries {
+        return Err(invalid_archive(format!(
+            "Archive has {} entries, the limit is {}",
+            entries.len(),
+            limits.max_entries
+        )));
+    }
+
+    let mut declared_size = 0u64;
+    for (name, size, compressed_size) in entries {
+        declared_size = declared_size.saturating_add(*size);
+        if exceeds_ratio(*size, *compressed_size, limits.max_compression_ratio) {
+            return Err(invalid_archive(format!(
+                "Entry {} has a compression ratio over {}",
+                name, limits.max_compression_ratio
+            )));
+        }
+    }
+
+    if declared_size > limits.max_total_size() {
+        return Err(invalid_archive(format!(
+            "Archive expands to {} MB, the limit is {} MB",
+            declared_size / (1024 * 1024),
+            limits.max_total_size_mb
+        )));
+    }
+
+    Ok(())
+}
+
+/// Bytes unpacked from an archive, checked against the limits while they are read
+/// Declared sizes can lie, so reading stops as soon as a limit is exceeded
+pub(super) struct Budget<'a> {
+    limits: &'a ExtractLimits,
+    cancel: &'a CancelToken,
+    used: u64,
+    /// Size of the archive file, when the bytes are checked against it as a whole
+    archive_size: Option<u64>,
+    buffer: Vec<u8>,
+}
+
+impl<'a> Budget<'a> {
+    pub(super) fn new(
+        limits: &'a ExtractLimits,
+        cancel: &'a CancelToken,
+        archive_size: Option<u64>,
+    ) -> Self {
+        Budget {
+            limits,
+            cancel,
+            used: 0,
+            archive_size,
+            buffer: vec![0u8; 64 * 1024],
+        }
+    }
+
+    /// Copies one entry to `writer`, checking the limits and cancellation after every chunk
+    /// `compressed_size` is the packed size of the entry, 0 when unknown
+    /// Read and write failures are turned into errors by `io_error`
+    pub(super) fn copy(
+        &mut self,
+        name: &str,
+        compressed_size: u64,
+        reader: &mut dyn Read,
+        writer: &mut dyn Write,
+        io_error: &dyn Fn(std::io::Error) -> AppError,
+    ) -> Result<u64, AppError> {
+        let mut copied = 0u64;
+        loop {
+            self.cancel.check()?;
+            let read = match reader.read(&mut self.buffer) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(io_error(e)),
+            };
+            writer.write_all(&self.buffer[..read]).map_err(io_error)?;
+            copied += read as u64;
+            self.check(name, copied, compressed_size)?;
+        }
+
+        self.used += copied;
+        Ok(copied)
+    }
+
+    fn check(&self, name: &str, copied: u64, compressed_size: u64) -> Result<(), AppError> {
+        let used = self.used + copied;
+        if used > self.limits.max_total_size() {
+            return Err(invalid_archive(format!(
+                "Archive expands past the {} MB limit",
+                self.limits.max_total_size_mb
+            )));
+        }
+        if exceeds_ratio(copied, compressed_size, self.limits.max_compression_ratio) {
+            return Err(invalid_archive(format!(
+                "Entry {} has a compression ratio over {}",
+                name, self.limits.max_compression_ratio
+            )));
+        }
+        if let Some(archive_size) = self.archive_size {
+            if exceeds_ratio(used, archive_size, self.limits.max_compression_ratio) {
+                return Err(invalid_archive(format!(
+                    "Archive has a compression ratio over {}",
+                    self.limits.max_compression_ratio
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes archive entries below the extract directory, applying the name and size policy
+struct EntryWriter<'a> {
+    extract_path: &'a Path,
+    budget: Budget<'a>,
+    entry_count: u64,
+    processed: u64,
+    on_progress: &'a mut dyn FnMut(u64, u64, u64),
+    report: ExtractReport,
+}
+
+impl<'a> EntryWriter<'a> {
+    fn new(
+        extract_path: &'a Path,
+        budget: Budget<'a>,
+        entry_count: usize,
+        on_progress: &'a mut dyn FnMut(u64, u64, u64),
+    ) -> Result<Self, AppError> {
+        std::fs::create_dir_all(extract_path).map_err(|e| {
+            tracing::error!("Error creating extract directory: {}", e);
+            AppError::io("Error creating extract directory", e)
+        })?;
+
+        Ok(EntryWriter {
+            extract_path,
+            budget,
+            entry_count: entry_count as u64,
+            processed: 0,
+            on_progress,
+            report: ExtractReport::default(),
+        })
+    }
+
+    fn skip(&mut self, name: &str, reason: String) {
+        self.report.skipped.push(SkippedEntry {
+            name: name.to_string(),
+            reason,
+        });
+    }
+
+    /// Writes or skips one entry and reports the progress
+    /// `compressed_size` is the packed size of the entry, 0 when unknown
+    fn write(
+        &mut self,
+        name: &str,
+        is_dir: bool,
+        is_symlink: bool,
+        compressed_size: u64,
+        reader: &mut dyn Read,
+    ) -> Result<(), AppError> {
+        self.write_entry(name, is_dir, is_symlink, compressed_size, reader)?;
+
+        self.processed += 1;
+        (self.on_progress)(self.processed, self.entry_count, self.budget.used);
+        Ok(())
+    }
+
+    fn write_entry(
+        &mut self,
+        name: &str,
+        is_dir: bool,
+        is_symlink: bool,
+        compressed_size: u64,
+        reader: &mut dyn Read,
+    ) -> Result<(), AppError> {
+        if is_symlink {
+            self.skip(name, "symlink".to_string());
+            return Ok(());
+        }
+
+        let relative_path = match sanitize_entry_name(name) {
+            Ok(path) => path,
+            Err(reason) => {
+                self.skip(name, reason);
+                return Ok(());
+            }
+        };
+        let out_path = self.extract_path.join(&relative_path);
+
+        if is_dir {
+            std::fs::create_dir_all(&out_path).map_err(|e| {
+                AppError::io(
+                    format!("Error creating directory {}", out_path.display()),
+                    e,
+                )
+            })?;
+            return Ok(());
+        }
+
+        if let Some(parent) = out_path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                AppError::io(format!("Error creating directory {}", parent.display()), e)
+            })?;
+        }
+        let mut out_file = std::fs::File::create(&out_path)
+            .map_err(|e| AppError::io(format!("Error creating file {}", out_path.display()), e))?;
+
+        self.budget
+            .copy(name, compressed_size, reader, &mut out_file, &|e| {
+                tracing::error!("Error extracting {}: {}", name, e);
+                extract_error(name, e)
+            })?;
+        self.report.extracted += 1;
+        Ok(())
+    }
+}
+
+/// Whether `size` bytes unpacked from `compressed_size` go over the ratio, 0 is an unknown packed size
+fn exceeds_ratio(size: u64, compressed_size: u64, max_ratio: u64) -> bool {
+    size >= RATIO_CHECK_MIN_SIZE && compressed_size > 0 && size / compressed_size > max_ratio
+}
+
+fn invalid_archive(message: impl Into<String>) -> AppError {
+    AppError::new(ErrorKind::InvalidArchive, message)
+}
+
+/// A failed entry copy is a corrupted archive unless writing the output failed
+fn extract_error(name: &str, error: std::io::Error) -> AppError {
+    match error.kind() {
+        std::io::ErrorKind::InvalidData | std::io::ErrorKind::UnexpectedEof => {
+            invalid_archive(format!("Error extracting {}: {}", name, error))
+        }
+        _ => AppError::io(format!("Error extracting {}", name), error),
+    }
+}
+
+/// Turns an entry name into a path relative to the extract directory
+/// Leading `/` and drive letters are stripped, `..` and other hostile names are rejected
+fn sanitize_entry_name(name: &str) -> Result<PathBuf, String> {
+    if name.contains('\0') {
+        return Err("name contains a NUL byte".to_string());
+    }
+
+    let mut path = PathBuf::new();
+    for part in name.split(['/', '\\']) {
+        match part {
+            "" | "." => continue,
+            ".." => return Err("path escapes the extract directory".to_string()),
+            // Drive letter of an absolute Windows path
+            _ if path.as_os_str().is_empty() && part.len() == 2 && part.ends_with(':') => continue,
+            _ if part.contains(':') => return Err("name contains ':'".to_string()),
+            _ => path.push(part),
+        }
+    }
+
+    // Anything the platform would still read as absolute or as a prefix is refused
+    if !path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err("path escapes the extract directory".to_string());
+    }
+    if path.as_os_str().is_empty() {
+        return Err("empty name".to_string());
+    }
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    /// Writes a zip with the given (name, content) files
+    fn write_zip(dir: &Path, files: &[(&str, &[u8])]) -> PathBuf {
+        let zip_path = dir.join("pack.zip");
+        let mut writer = zip::ZipWriter::new(std::fs::File::create(&zip_path).unwrap());
+        for (name, content) in files {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(content).unwrap();
+        }
+        writer.finish().unwrap();
+        zip_path
+    }
+
+    fn extract(zip_path: &Path, limits: &ExtractLimits) -> Result<ExtractReport, AppError> {
+        let extract_path = zip_path.with_file_name("pack");
+        extract_archive(
+            zip_path,
+            &extract_path,
+            limits,
+            &CancelToken::default(),
+            &mut |_, _, _| {},
+        )
+    }
+
+    #[test]
+    fn hostile_names_are_sanitised_or_skipped() {
+        let dir = TempDir::new("extract-names");
+        let zip_path = write_zip(
+            &dir,
+            &[
+                ("Song/chart.sm", b"ok"),
+                ("../evil.txt", b"evil"),
+                ("Song/../../evil.txt", b"evil"),
+                ("/Absolute/chart.sm", b"abs"),
+                ("C:\\Windows\\chart.sm", b"drive"),
+            ],
+        );
+
+        let report = extract(&zip_path, &ExtractLimits::default()).unwrap();
+
+        let extract_path = dir.join("pack");
+        assert!(extract_path.join("Song/chart.sm").exists());
+        assert!(extract_path.join("Absolute/chart.sm").exists());
+        assert!(extract_path.join("Windows/chart.sm").exists());
+        assert!(!dir.join("evil.txt").exists());
+        assert_eq!(report.extracted, 3);
+        assert_eq!(
+            report
+                .skipped
+                .iter()
+                .map(|s| s.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["../evil.txt", "Song/../../evil.txt"]
+        );
+    }
+
+    #[test]
+    fn progress_counts_entries_and_bytes() {
+        let dir = TempDir::new("extract-progress");
+        let zip_path = write_zip(
+            &dir,
+            &[
+                ("Song/chart.sm", b"chart"),
+                ("../evil.txt", b"evil"),
+                ("Song/song.ogg", b"audio"),
+            ],
+        );
+
+        let mut calls = Vec::new();
+        extract_archive(
+            &zip_path,
+            &dir.join("pack"),
+            &ExtractLimits::default(),
+            &CancelToken::default(),
+            &mut |done, total, bytes| calls.push((done, total, bytes)),
+        )
+        .unwrap();
+
+        // Skipped entries count as processed but write nothing
+        assert_eq!(calls, vec![(1, 3, 5), (2, 3, 5), (3, 3, 10)]);
+    }
+
+    #[test]
+    fn symlinks_are_not_extracted() {
+        let dir = TempDir::new("extract-symlink");
+        let zip_path = dir.join("pack.zip");
+        let mut writer = zip::ZipWriter::new(std::fs::File::create(&zip_path).unwrap());
+        writer
+            .add_symlink("Song/link", "/etc/passwd", SimpleFileOptions::default())
+            .unwrap();
+        writer.finish().unwrap();
+
+        let report = extract(&zip_path, &ExtractLimits::default()).unwrap();
+
+        assert!(!dir.join("pack/Song/link").exists());
+        assert_eq!(report.skipped[0].reason, "symlink");
+    }
+
+    #[test]
+    fn archive_bombs_are_rejected() {
+        let dir = TempDir::new("extract-bomb");
+        let zeros = vec![0u8; 4 * 1024 * 1024];
+        let zip_path = write_zip(&dir, &[("bomb.bin", &zeros)]);
+
+        assert!(extract(&zip_path, &ExtractLimits::default())
+            .unwrap_err()
+            .message
+            .contains("compression ratio"));
+
+        let small_limit = ExtractLimits {
+            max_total_size_mb: 1,
+            max_compression_ratio: u64::MAX,
+            ..ExtractLimits::default()
+        };
+        assert!(extract(&zip_path, &small_limit)
+            .unwrap_err()
+            .message
+            .contains("limit"));
+        assert!(!dir.join("pack/bomb.bin").exists());
+
+        let few_entries = ExtractLimits {
+            max_entries: 1,
+            ..ExtractLimits::default()
+        };
+        let zip_path = write_zip(&dir, &[("a.sm", b"a"), ("b.sm", b"b")]);
+        assert!(extract(&zip_path, &few_entries)
+            .unwrap_err()
+            .message
+            .contains("entries"));
+    }
+
+    /// Writes a 7z archive with the given (name, content, unix mode) entries
+    fn write_7z(dir: &Path, files: &[(&str, &[u8], Option<u32>)]) -> PathBuf {
+        let archive_path = dir.join("pack.7z");
+        let mut writer =
+            sevenz_rust::SevenZWriter::new(std::fs::File::create(&archive_path).unwrap()).unwrap();
+        for (name, content, mode) in files {
+            let mut entry = sevenz_rust::SevenZArchiveEntry::new();
+            entry.name = name.to_string();
+            entry.has_stream = true;
+            if let Some(mode) = mode {
+                entry.has_windows_attributes = true;
+                entry.windows_attributes = 0x8000 | (mode << 16);
+            }
+            writer.push_archive_entry(entry, Some(*content)).unwrap();
+        }
+        writer.finish().unwrap();
+        archive_path
+    }
+
+    #[test]
+    fn compression_ratio_is_checked_on_the_bytes_written() {
+        let dir = TempDir::new("extract-ratio");
+        let limits = ExtractLimits::default();
+        let cancel = CancelToken::default();
+        let mut on_progress = |_, _, _| {};
+        let budget = Budget::new(&limits, &cancel, None);
+        let mut writer = EntryWriter::new(&dir, budget, 2, &mut on_progress).unwrap();
+
+        // Headers claiming a 1 KB entry that unpacks to 4 MB
+        let zeros = vec![0u8; 4 * 1024 * 1024];
+        let error = writer
+            .write("bomb.bin", false, false, 1024, &mut zeros.as_slice())
+            .unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidArchive);
+        assert!(error.message.contains("compression ratio"), "{}", error);
+        assert!(std::fs::metadata(dir.join("bomb.bin")).unwrap().len() < zeros.len() as u64);
+
+        writer
+            .write("fine.bin", false, false, 64 * 1024, &mut zeros.as_slice())
+            .unwrap();
+        assert_eq!(writer.report.extracted, 1);
+    }
+
+    #[test]
+    fn formats_are_detected_by_magic_bytes() {
+        let dir = TempDir::new("extract-magic");
+        let archive_path = write_7z(&dir, &[("Song/chart.sm", b"ok", None)]);
+        let misnamed = dir.join("pack.zip");
+        std::fs::rename(&archive_path, &misnamed).unwrap();
+
+        assert_eq!(
+            ArchiveFormat::detect(&misnamed),
+            Ok(ArchiveFormat::SevenZip)
+        );
+        assert_eq!(
+            ArchiveFormat::from_magic(b"Rar!\x1A\x07\x01\x00"),
+            Some(ArchiveFormat::Rar)
+        );
+        assert_eq!(ArchiveFormat::from_magic(b"<html>"), None);
+
+        extract(&misnamed, &ExtractLimits::default()).unwrap();
+        assert_eq!(
+            std::fs::read(dir.join("pack/Song/chart.sm")).unwrap(),
+            b"ok"
+        );
+    }
+
+    #[test]
+    fn seven_zip_entries_follow_the_same_policy() {
+        let dir = TempDir::new("extract-7z");
+        let archive_path = write_7z(
+            &dir,
+            &[
+                ("../evil.txt", b"evil", None),
+                ("Song/link", b"/etc/passwd", Some(0o120777)),
+                ("Song/chart.sm", b"ok", Some(0o100644)),
+            ],
+        );
+
+        let report = extract(&archive_path, &ExtractLimits::default()).unwrap();
+
+        assert!(!dir.join("evil.txt").exists());
+        assert!(!dir.join("pack/Song/link").exists());
+        // Skipped entries are consumed so the next entry of the solid block reads its own data
+        assert_eq!(
+            std::fs::read(dir.join("pack/Song/chart.sm")).unwrap(),
+            b"ok"
+        );
+        assert_eq!(report.extracted, 1);
+        assert_eq!(report.skipped.len(), 2);
+    }
+
+    #[test]
+    fn failed_extractions_leave_nothing_behind() {
+        let dir = TempDir::new("extract-cleanup");
+        let zeros = vec![0u8; 4 * 1024 * 1024];
+        let zip_path = write_zip(&dir, &[("Song/chart.sm", b"ok"), ("Song/bomb.bin", &zeros)]);
+        let small_limit = ExtractLimits {
+            max_total_size_mb: 1,
+            max_compression_ratio: u64::MAX,
+            ..ExtractLimits::default()
+        };
+
+        assert!(extract(&zip_path, &small_limit).is_err());
+        assert!(!dir.join("pack").exists());
+        assert!(!dir.join("pack.partial").exists());
+
+        // A previous extraction stays until a new one completes
+        std::fs::create_dir_all(dir.join("pack/Old")).unwrap();
+        assert!(extract(&zip_path, &small_limit).is_err());
+        assert!(dir.join("pack/Old").exists());
+
+        extract(
+            &zip_path,
+            &ExtractLimits {
+                max_compression_ratio: u64::MAX,
+                ..ExtractLimits::default()
+            },
+        )
+        .unwrap();
+        assert!(!dir.join("pack/Old").exists());
+        assert!(dir.join("pack/Song/bomb.bin").exists());
+        assert!(!dir.join("pack.partial").exists());
+    }
+}
@@ -0,0 +1,310 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::types::{Pack, PacksResponse};
+use super::utils::SortField;
+use crate::error::{AppError, ErrorKind};
+
+/// Inclusive range, an unset bound is open
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ValueRange {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl ValueRange {
+    fn contains(&self, value: f64) -> bool {
+        self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max)
+    }
+
+    fn validate(&self, name: &str) -> Result<(), AppError> {
+        for bound in [self.min, self.max].into_iter().flatten() {
+            if !bound.is_finite() || bound < 0.0 {
+                return Err(AppError::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "Invalid {} filter: bounds must be non-negative numbers",
+                        name
+                    ),
+                ));
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min, self.max) {
+            if min > max {
+                return Err(AppError::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "Invalid {} filter: minimum {} is above maximum {}",
+                        name, min, max
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Filters of the pack list besides the free-text search
+/// Tag inclusion is sent to the API, everything is also checked on the fetched page
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PackFilters {
+    /// MSD ranges keyed by `overall` or a skillset name
+    pub msd: BTreeMap<String, ValueRange>,
+    /// Packs must have all of these tags
+    pub include_tags: Vec<String>,
+    /// Packs must have none of these tags
+    pub exclude_tags: Vec<String>,
+    pub exclude_nsfw: bool,
+    pub song_count: ValueRange,
+    /// Pack size range in MB
+    pub size_mb: ValueRange,
+}
+
+impl PackFilters {
+    pub fn validate(&self) -> Result<(), AppError> {
+        for (field, range) in &self.msd {
+            if msd_field(field).is_none() {
+                return Err(AppError::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "Invalid MSD filter field: {}. Valid options: {}",
+                        field,
+                        SortField::list(SortField::ALL.into_iter().filter(SortField::is_msd))
+                    ),
+                ));
+            }
+            range.validate(field)?;
+        }
+        self.song_count.validate("song count")?;
+        self.size_mb.validate("size")?;
+
+        if self
+            .include_tags
+            .iter()
+            .chain(&self.exclude_tags)
+            .any(|tag| tag.trim().is_empty())
+        {
+            return Err(AppError::new(
+                ErrorKind::InvalidInput,
+                "Tag filters can't be empty",
+            ));
+        }
+        if let Some(tag) = self.include_tags.iter().find(|tag| {
+            self.exclude_tags
+                .iter()
+                .any(|other| other.eq_ignore_ascii_case(tag))
+        }) {
+            return Err(AppError::new(
+                ErrorKind::InvalidInput,
+                format!("Tag {} is both included and excluded", tag),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Whether some filters are not supported by the API and have to be applied to the fetched pages
+    pub fn needs_local_filtering(&self) -> bool {
+        !self.msd.is_empty()
+            || !self.exclude_tags.is_empty()
+            || self.exclude_nsfw
+            || self.song_count != ValueRange::default()
+            || self.size_mb != ValueRange::default()
+    }
+
+    /// Adds the filters the API understands to the request
+    pub fn append_to_query(&self, url: &mut reqwest::Url) {
+        if !self.include_tags.is_empty() {
+            let tags: Vec<&str> = self.include_tags.iter().map(|tag| tag.trim()).collect();
+            url.query_pairs_mut()
+                .append_pair("filter[tags]", &tags.join(","));
+        }
+    }
+
+    /// Drops the packs of an API page that fail the filters, the meta tells how many were dropped
+    /// and whether the totals are only an upper bound
+    pub fn apply_to_page(&self, response: &mut PacksResponse) {
+        response.meta.totals_are_estimates = self.needs_local_filtering();
+
+        let fetched = response.data.len() as u64;
+        response.data.retain(|pack| self.matches(pack));
+        let kept = response.data.len() as u64;
+        if kept == fetched {
+            return;
+        }
+
+        let meta = &mut response.meta;
+        meta.filtered_out = fetched - kept;
+        meta.from = meta.from.filter(|_| kept > 0);
+        meta.to = meta.from.map(|from| from + kept - 1);
+    }
+
+    /// Whether a pack passes every filter, expects validated filters
+    pub fn matches(&self, pack: &Pack) -> bool {
+        let has_tag = |name: &String| {
+            pack.tags
+                .iter()
+                .any(|tag| tag.name.eq_ignore_ascii_case(name.trim()))
+        };
+
+        self.msd.iter().all(|(field, range)| {
+            msd_field(field).is_some_and(|field| range.contains(pack.field_value(field)))
+        }) && self.include_tags.iter().all(has_tag)
+            && !self.exclude_tags.iter().any(has_tag)
+            && !(self.exclude_nsfw && pack.contains_nsfw)
+            && self.song_count.contains(pack.song_count as f64)
+            // A size that can't be read only fails size filters
+            && (self.size_mb == ValueRange::default()
+                || parse_size_mb(&pack.size).is_some_and(|size| self.size_mb.contains(size)))
+    }
+}
+
+fn msd_field(name: &str) -> Option<SortField> {
+    name.parse::<SortField>().ok().filter(SortField::is_msd)
+}
+
+/// Reads a pack size such as `1.2 GB` or `512MB` in MB
+pub fn parse_size_mb(size: &str) -> Option<f64> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ','))
+        .unwrap_or(size.len());
+    let value: f64 = size[..split].replace(',', ".").parse().ok()?;
+    let factor = match size[split..].trim().to_ascii_uppercase().as_str() {
+        "B" => 1.0 / (1024.0 * 1024.0),
+        "KB" | "KIB" => 1.0 / 1024.0,
+        "" | "MB" | "MIB" => 1.0,
+        "GB" | "GIB" => 1024.0,
+        "TB" | "TIB" => 1024.0 * 1024.0,
+        _ => return None,
+    };
+    Some(value * factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pack(overall: f64, stream: f64, tags: &[&str], nsfw: bool, size: &str) -> Pack {
+        Pack {
+            overall,
+            stream,
+            contains_nsfw: nsfw,
+            size: size.to_string(),
+            ..Pack::fixture(1, "Pack", tags)
+        }
+    }
+
+    #[test]
+    fn parses_pack_sizes() {
+        assert_eq!(parse_size_mb("512 MB"), Some(512.0));
+        assert_eq!(parse_size_mb("1.5GB"), Some(1536.0));
+        assert_eq!(parse_size_mb("2,5 gb"), Some(2560.0));
+        assert_eq!(parse_size_mb("huge"), None);
+    }
+
+    #[test]
+    fn packs_are_matched_against_every_filter() {
+        let filters = PackFilters {
+            msd: BTreeMap::from([(
+                "stream".to_string(),
+                ValueRange {
+                    min: Some(20.0),
+                    max: Some(25.0),
+                },
+            )]),
+            include_tags: vec!["Dumps".to_string()],
+            exclude_tags: vec!["Memes".to_string()],
+            exclude_nsfw: true,
+            size_mb: ValueRange {
+                min: None,
+                max: Some(1024.0),
+            },
+            ..PackFilters::default()
+        };
+        assert!(filters.validate().is_ok());
+
+        assert!(filters.matches(&pack(22.0, 22.0, &["dumps"], false, "800 MB")));
+        assert!(!filters.matches(&pack(22.0, 19.0, &["Dumps"], false, "800 MB")));
+        assert!(!filters.matches(&pack(22.0, 22.0, &[], false, "800 MB")));
+        assert!(!filters.matches(&pack(22.0, 22.0, &["Dumps", "Memes"], false, "800 MB")));
+        assert!(!filters.matches(&pack(22.0, 22.0, &["Dumps"], true, "800 MB")));
+        assert!(!filters.matches(&pack(22.0, 22.0, &["Dumps"], false, "1.2 GB")));
+    }
+
+    #[test]
+    fn filtered_pages_report_the_dropped_packs() {
+        let mut response: PacksResponse = serde_json::from_value(serde_json::json!({
+            "data": [],
+            "links": { "first": "", "last": "", "prev": null, "next": null },
+            "meta": {
+                "current_page": 2,
+                "from": 13,
+                "last_page": 5,
+                "links": [],
+                "path": "",
+                "per_page": 12,
+                "to": 15,
+                "total": 51
+            }
+        }))
+        .unwrap();
+        response.data = vec![
+            pack(22.0, 22.0, &["Dumps"], false, "800 MB"),
+            pack(22.0, 22.0, &[], true, "800 MB"),
+            pack(22.0, 22.0, &["Dumps"], true, "800 MB"),
+        ];
+        let filters = PackFilters {
+            exclude_nsfw: true,
+            ..PackFilters::default()
+        };
+
+        filters.apply_to_page(&mut response);
+        assert_eq!(response.data.len(), 1);
+        assert_eq!(response.meta.filtered_out, 2);
+        assert_eq!((response.meta.from, response.meta.to), (Some(13), Some(13)));
+        assert_eq!(response.meta.total, 51);
+        assert!(response.meta.totals_are_estimates);
+
+        PackFilters {
+            include_tags: vec!["Memes".to_string()],
+            ..PackFilters::default()
+        }
+        .apply_to_page(&mut response);
+        assert!(response.data.is_empty());
+        // Tags are filtered by the API, its totals are exact
+        assert!(!response.meta.totals_are_estimates);
+        assert_eq!((response.meta.from, response.meta.to), (None, None));
+    }
+
+    #[test]
+    fn invalid_filters_are_refused() {
+        let filters = PackFilters {
+            msd: BTreeMap::from([("popularity".to_string(), ValueRange::default())]),
+            ..PackFilters::default()
+        };
+        let error = filters.validate().unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidInput);
+        assert!(error
+            .message
+            .starts_with("Invalid MSD filter field: popularity"));
+
+        let filters = PackFilters {
+            song_count: ValueRange {
+                min: Some(50.0),
+                max: Some(10.0),
+            },
+            ..PackFilters::default()
+        };
+        assert!(filters.validate().is_err());
+
+        let filters = PackFilters {
+            include_tags: vec!["Dumps".to_string()],
+            exclude_tags: vec!["dumps".to_string()],
+            ..PackFilters::default()
+        };
+        assert!(filters.validate().is_err());
+    }
+}
@@ -0,0 +1,195 @@
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::Path;
+
+use super::extract::{open_7z, open_zip, ArchiveFormat, Budget, ExtractLimits};
+use super::jobs::CancelToken;
+use crate::error::{AppError, ErrorKind};
+
+/// SHA-256 of a file as lowercase hex
+pub fn sha256_file(path: &Path) -> Result<String, AppError> {
+    let mut file = std::fs::File::open(path)
+        .map_err(|e| AppError::io(format!("Error opening {}", path.display()), e))?;
+
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| AppError::io(format!("Error reading {}", path.display()), e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Reads every entry of the archive, failing on the first CRC mismatch
+/// Nothing is written to disk, so a corrupted archive never gets partially installed
+/// The extraction limits apply to the bytes read, an archive bomb is rejected before it is unpacked
+pub fn verify_archive(
+    archive_path: &Path,
+    limits: &ExtractLimits,
+    cancel: &CancelToken,
+) -> Result<(), AppError> {
+    match ArchiveFormat::detect(archive_path)? {
+        ArchiveFormat::Zip => verify_zip(archive_path, limits, cancel),
+        ArchiveFormat::SevenZip => verify_7z(archive_path, limits, cancel),
+        // Refused with a clear error by the extraction
+        ArchiveFormat::Rar => Ok(()),
+    }
+}
+
+/// A failed read while verifying is a corrupted entry
+fn corrupted_entry(name: &str, error: std::io::Error) -> AppError {
+    AppError::new(
+        ErrorKind::InvalidArchive,
+        format!("Corrupted entry {}: {}", name, error),
+    )
+    .with_details(name)
+}
+
+fn verify_zip(
+    zip_path: &Path,
+    limits: &ExtractLimits,
+    cancel: &CancelToken,
+) -> Result<(), AppError> {
+    let mut archive = open_zip(zip_path, limits)?;
+
+    let mut budget = Budget::new(limits, cancel, None);
+    for index in 0..archive.len() {
+        cancel.check()?;
+
+        let mut entry = archive.by_index(index).map_err(|e| {
+            AppError::new(
+                ErrorKind::InvalidArchive,
+                format!("Error reading zip entry: {}", e),
+            )
+        })?;
+        let name = entry.name().to_string();
+        let compressed_size = entry.compressed_size();
+        // The zip reader checks the CRC once the entry has been read to the end
+        budget.copy(
+            &name,
+            compressed_size,
+            &mut entry,
+            &mut std::io::sink(),
+            &|e| corrupted_entry(&name, e),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn verify_7z(
+    archive_path: &Path,
+    limits: &ExtractLimits,
+    cancel: &CancelToken,
+) -> Result<(), AppError> {
+    let (mut reader, archive_size) = open_7z(archive_path, limits)?;
+
+    let mut budget = Budget::new(limits, cancel, Some(archive_size));
+    let mut error = None;
+    reader
+        .for_each_entries(|entry, entry_reader| {
+            let result = budget.copy(entry.name(), 0, entry_reader, &mut std::io::sink(), &|e| {
+                corrupted_entry(entry.name(), e)
+            });
+            match result {
+                Ok(_) => Ok(true),
+                Err(e) => {
+                    error = Some(e);
+                    Ok(false)
+                }
+            }
+        })
+        .map_err(|e| {
+            AppError::new(
+                ErrorKind::InvalidArchive,
+                format!("Corrupted 7z archive: {}", e),
+            )
+        })?;
+
+    match error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    #[test]
+    fn sha256_matches_known_digest() {
+        let dir = TempDir::new("sha");
+        let path = dir.join("pack.zip");
+        std::fs::write(&path, b"abc").unwrap();
+
+        assert_eq!(
+            sha256_file(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn corrupted_zip_entry_fails_the_crc_check() {
+        let dir = TempDir::new("crc");
+        let path = dir.join("pack.zip");
+        let mut writer = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        let stored =
+            SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        writer.start_file("Song/chart.sm", stored).unwrap();
+        writer.write_all(b"#TITLE:Intact;").unwrap();
+        writer.finish().unwrap();
+
+        assert!(verify_archive(&path, &ExtractLimits::default(), &CancelToken::default()).is_ok());
+
+        // Flip a byte of the stored entry's data
+        let mut bytes = std::fs::read(&path).unwrap();
+        let offset = bytes
+            .windows(6)
+            .position(|window| window == b"Intact")
+            .unwrap();
+        bytes[offset] = b'X';
+        std::fs::write(&path, bytes).unwrap();
+
+        let error =
+            verify_archive(&path, &ExtractLimits::default(), &CancelToken::default()).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidArchive);
+        assert!(error.message.contains("Song/chart.sm"), "{}", error);
+    }
+
+    #[test]
+    fn archive_bombs_are_rejected_before_being_read() {
+        let dir = TempDir::new("verify-bomb");
+        let path = dir.join("pack.zip");
+        let mut writer = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        writer
+            .start_file("bomb.bin", SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(&vec![0u8; 4 * 1024 * 1024]).unwrap();
+        writer.finish().unwrap();
+
+        let error =
+            verify_archive(&path, &ExtractLimits::default(), &CancelToken::default()).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidArchive);
+        assert!(error.message.contains("compression ratio"), "{}", error);
+
+        // Nothing is read once the job is cancelled
+        let cancel = CancelToken::default();
+        cancel.cancel();
+        let no_ratio = ExtractLimits {
+            max_compression_ratio: u64::MAX,
+            ..ExtractLimits::default()
+        };
+        assert!(verify_archive(&path, &no_ratio, &cancel)
+            .unwrap_err()
+            .is(ErrorKind::Cancelled));
+    }
+}
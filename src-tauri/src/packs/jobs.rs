@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::error::{AppError, ErrorKind};
+
+/// Shared flag telling a running download to stop
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    notify: Arc<tokio::sync::Notify>,
+}
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Returns a `Cancelled` error once the job has been cancelled, for use between steps
+    pub fn check(&self) -> Result<(), AppError> {
+        if self.is_cancelled() {
+            Err(AppError::cancelled())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Resolves when the job is cancelled
+    pub async fn cancelled(&self) {
+        let notified = self.notify.notified();
+        if self.is_cancelled() {
+            return;
+        }
+        notified.await;
+    }
+}
+
+/// Running download jobs by pack id, held in Tauri managed state
+#[derive(Debug, Default)]
+pub struct DownloadJobs {
+    jobs: Mutex<HashMap<u64, CancelToken>>,
+}
+
+impl DownloadJobs {
+    /// Registers a job for the pack, fails if one is already running
+    pub fn start(&self, pack_id: u64) -> Result<CancelToken, AppError> {
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        if jobs.contains_key(&pack_id) {
+            return Err(AppError::new(
+                ErrorKind::Conflict,
+                format!("Pack {} is already being downloaded", pack_id),
+            ));
+        }
+
+        let token = CancelToken::default();
+        jobs.insert(pack_id, token.clone());
+        Ok(token)
+    }
+
+    pub fn finish(&self, pack_id: u64) {
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        jobs.remove(&pack_id);
+    }
+
+    /// Cancels the pack's job, returns whether one was running
+    pub fn cancel(&self, pack_id: u64) -> bool {
+        let jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        match jobs.get(&pack_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Stops a running pack download, its temporary files are removed by the download itself
+#[tauri::command]
+#[tracing::instrument(skip(jobs))]
+pub fn cancel_download(
+    pack_id: u64,
+    jobs: tauri::State<'_, DownloadJobs>,
+) -> Result<bool, AppError> {
+    tracing::info!("Cancelling download of pack {}", pack_id);
+    Ok(jobs.cancel(pack_id))
+}
@@ -1,4 +1,13 @@
-pub mod types;
-pub mod utils;
 pub mod api;
+pub mod catalog;
+pub mod client;
 pub mod download;
+pub mod extract;
+pub mod filters;
+pub mod integrity;
+pub mod jobs;
+pub mod queue;
+pub mod resume;
+pub mod types;
+pub mod uninstall;
+pub mod utils;
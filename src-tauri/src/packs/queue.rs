@@ -0,0 +1,307 @@
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::Emitter;
+
+use super::jobs::CancelToken;
+use crate::error::{AppError, ErrorKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueueState {
+    Queued,
+    Active,
+    Paused,
+}
+
+/// A pack in the download queue, as sent to the frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueEntry {
+    pub pack_id: u64,
+    pub pack_name: Option<String>,
+    pub state: QueueState,
+}
+
+struct Slot {
+    entry: QueueEntry,
+    /// Set while the pack's archive is downloading, triggered to pause it
+    pause: Option<CancelToken>,
+}
+
+/// Packs waiting for or holding one of the download slots, in queue order
+/// Held in Tauri managed state
+#[derive(Default)]
+pub struct DownloadQueue {
+    slots: Mutex<Vec<Slot>>,
+    changed: tokio::sync::Notify,
+}
+
+impl DownloadQueue {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Slot>> {
+        self.slots.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn not_queued(pack_id: u64) -> AppError {
+        AppError::new(
+            ErrorKind::NotFound,
+            format!("Pack {} is not in the download queue", pack_id),
+        )
+    }
+
+    fn slot_mut(slots: &mut [Slot], pack_id: u64) -> Result<&mut Slot, AppError> {
+        slots
+            .iter_mut()
+            .find(|slot| slot.entry.pack_id == pack_id)
+            .ok_or_else(|| Self::not_queued(pack_id))
+    }
+
+    /// Adds a pack at the end of the queue
+    pub fn enqueue(&self, pack_id: u64, pack_name: Option<String>) {
+        self.lock().push(Slot {
+            entry: QueueEntry {
+                pack_id,
+                pack_name,
+                state: QueueState::Queued,
+            },
+            pause: None,
+        });
+        self.changed.notify_waiters();
+    }
+
+    /// Removes a finished, failed or cancelled pack, freeing its slot
+    pub fn remove(&self, pack_id: u64) {
+        self.lock().retain(|slot| slot.entry.pack_id != pack_id);
+        self.changed.notify_waiters();
+    }
+
+    pub fn entries(&self) -> Vec<QueueEntry> {
+        self.lock().iter().map(|slot| slot.entry.clone()).collect()
+    }
+
+    /// Makes the pack active if a slot is free and no queued pack is ahead of it
+    /// Returns the token pausing its download
+    fn try_activate(&self, pack_id: u64, max_active: usize) -> Option<CancelToken> {
+        let mut slots = self.lock();
+
+        let active = slots
+            .iter()
+            .filter(|slot| slot.entry.state == QueueState::Active)
+            .count();
+        if active >= max_active {
+            return None;
+        }
+
+        let next = slots
+            .iter_mut()
+            .find(|slot| slot.entry.state == QueueState::Queued)?;
+        if next.entry.pack_id != pack_id {
+            return None;
+        }
+
+        let pause = CancelToken::default();
+        next.entry.state = QueueState::Active;
+        next.pause = Some(pause.clone());
+        Some(pause)
+    }
+
+    /// Waits until the pack gets a download slot
+    /// Returns the token pausing its download, or a `Cancelled` error if the job is cancelled first
+    pub async fn wait_for_turn(
+        &self,
+        pack_id: u64,
+        cancel: &CancelToken,
+    ) -> Result<CancelToken, AppError> {
+        loop {
+            let changed = self.changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+
+            let max_active = crate::settings::Settings::load_or_default()
+                .max_concurrent_downloads
+                .max(1);
+            if let Some(pause) = self.try_activate(pack_id, max_active) {
+                return Ok(pause);
+            }
+
+            tokio::select! {
+                _ = changed => {}
+                _ = cancel.cancelled() => return Err(AppError::cancelled()),
+            }
+        }
+    }
+
+    /// Marks the end of the pack's download stage, it can no longer be paused
+    pub fn download_finished(&self, pack_id: u64) {
+        if let Ok(slot) = Self::slot_mut(&mut self.lock(), pack_id) {
+            // A pause arriving as the download completed is ignored
+            slot.entry.state = QueueState::Active;
+            slot.pause = None;
+        }
+    }
+
+    /// Pauses a queued pack, or stops an active download where it is so it can resume later
+    pub fn pause(&self, pack_id: u64) -> Result<(), AppError> {
+        let mut slots = self.lock();
+        let slot = Self::slot_mut(&mut slots, pack_id)?;
+
+        match slot.entry.state {
+            QueueState::Paused => return Ok(()),
+            QueueState::Queued => {}
+            QueueState::Active => {
+                let Some(pause) = slot.pause.take() else {
+                    return Err(AppError::new(
+                        ErrorKind::Conflict,
+                        format!("Pack {} is being installed and cannot be paused", pack_id),
+                    ));
+                };
+                pause.cancel();
+            }
+        }
+
+        slot.entry.state = QueueState::Paused;
+        drop(slots);
+        self.changed.notify_waiters();
+        Ok(())
+    }
+
+    /// Puts a paused pack back in the queue
+    pub fn resume(&self, pack_id: u64) -> Result<(), AppError> {
+        let mut slots = self.lock();
+        let slot = Self::slot_mut(&mut slots, pack_id)?;
+        if slot.entry.state == QueueState::Paused {
+            slot.entry.state = QueueState::Queued;
+        }
+        drop(slots);
+        self.changed.notify_waiters();
+        Ok(())
+    }
+
+    /// Moves a pack to the given position in the queue (0 is the front)
+    pub fn move_to(&self, pack_id: u64, position: usize) -> Result<(), AppError> {
+        let mut slots = self.lock();
+        let index = slots
+            .iter()
+            .position(|slot| slot.entry.pack_id == pack_id)
+            .ok_or_else(|| Self::not_queued(pack_id))?;
+
+        let slot = slots.remove(index);
+        let position = position.min(slots.len());
+        slots.insert(position, slot);
+        drop(slots);
+        self.changed.notify_waiters();
+        Ok(())
+    }
+}
+
+/// Sends the current queue to the frontend as a `download-queue` event
+pub fn emit_queue(app: &tauri::AppHandle, queue: &DownloadQueue) {
+    let _ = app.emit("download-queue", queue.entries());
+}
+
+#[tauri::command]
+pub fn get_download_queue(
+    queue: tauri::State<'_, DownloadQueue>,
+) -> Result<Vec<QueueEntry>, AppError> {
+    Ok(queue.entries())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip(app, queue))]
+pub fn pause_download(
+    app: tauri::AppHandle,
+    queue: tauri::State<'_, DownloadQueue>,
+    pack_id: u64,
+) -> Result<(), AppError> {
+    tracing::info!("Pausing pack {}", pack_id);
+    queue.pause(pack_id)?;
+    emit_queue(&app, &queue);
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip(app, queue))]
+pub fn resume_download(
+    app: tauri::AppHandle,
+    queue: tauri::State<'_, DownloadQueue>,
+    pack_id: u64,
+) -> Result<(), AppError> {
+    tracing::info!("Resuming pack {}", pack_id);
+    queue.resume(pack_id)?;
+    emit_queue(&app, &queue);
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip(app, queue))]
+pub fn move_download(
+    app: tauri::AppHandle,
+    queue: tauri::State<'_, DownloadQueue>,
+    pack_id: u64,
+    position: usize,
+) -> Result<(), AppError> {
+    tracing::info!("Moving pack {} to position {}", pack_id, position);
+    queue.move_to(pack_id, position)?;
+    emit_queue(&app, &queue);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn states(queue: &DownloadQueue) -> Vec<(u64, QueueState)> {
+        queue
+            .entries()
+            .iter()
+            .map(|entry| (entry.pack_id, entry.state))
+            .collect()
+    }
+
+    #[test]
+    fn packs_start_in_queue_order_up_to_the_limit() {
+        let queue = DownloadQueue::default();
+        for pack_id in 1..=3 {
+            queue.enqueue(pack_id, None);
+        }
+
+        // Pack 2 has to wait for pack 1 even when a slot is free
+        assert!(queue.try_activate(2, 2).is_none());
+        assert!(queue.try_activate(1, 2).is_some());
+        assert!(queue.try_activate(2, 2).is_some());
+        assert!(queue.try_activate(3, 2).is_none());
+
+        queue.remove(1);
+        assert!(queue.try_activate(3, 2).is_some());
+    }
+
+    #[test]
+    fn paused_and_moved_packs_change_the_order() {
+        let queue = DownloadQueue::default();
+        for pack_id in 1..=3 {
+            queue.enqueue(pack_id, None);
+        }
+
+        queue.pause(1).unwrap();
+        queue.move_to(3, 0).unwrap();
+        assert_eq!(
+            states(&queue),
+            vec![
+                (3, QueueState::Queued),
+                (1, QueueState::Paused),
+                (2, QueueState::Queued)
+            ]
+        );
+
+        assert!(queue.try_activate(3, 1).is_some());
+        let pause = queue.try_activate(2, 2).unwrap();
+
+        // Pausing an active download stops it and gives its slot back
+        queue.pause(2).unwrap();
+        assert!(pause.is_cancelled());
+        queue.resume(1).unwrap();
+        assert!(queue.try_activate(1, 2).is_some());
+
+        // Once installing, a pack can no longer be paused
+        queue.download_finished(3);
+        assert!(queue.pause(3).is_err());
+    }
+}
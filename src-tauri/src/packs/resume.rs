@@ -0,0 +1,520 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+use super::jobs::CancelToken;
+use crate::error::{AppError, ErrorKind};
+
+/// Sidecar written next to a `.part` file, used to decide whether it can be resumed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PartialDownload {
+    url: String,
+    etag: Option<String>,
+    total_size: Option<u64>,
+}
+
+/// `<file>.part`, where the download is written until it completes
+pub fn part_path(file_path: &Path) -> PathBuf {
+    let mut name = file_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    file_path.with_file_name(name)
+}
+
+/// `<file>.part.json`, the sidecar describing the partial download
+pub fn sidecar_path(file_path: &Path) -> PathBuf {
+    let mut name = file_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".part.json");
+    file_path.with_file_name(name)
+}
+
+/// Downloads `url` to `file_path`, resuming a previous partial download when possible
+/// Progress is reported as (downloaded bytes, total bytes or 0 when unknown)
+/// Stops with a `Cancelled` error as soon as `cancel` is triggered
+pub async fn download_resumable(
+    client: &reqwest::Client,
+    url: &str,
+    file_path: &Path,
+    cancel: &CancelToken,
+    mut on_progress: impl FnMut(u64, u64),
+) -> Result<u64, AppError> {
+    let part_path = part_path(file_path);
+    let sidecar_path = sidecar_path(file_path);
+
+    let mut resume_from = resumable_size(url, &part_path, &sidecar_path).await;
+    let previous = read_sidecar(&sidecar_path).await;
+
+    // A second attempt is made from zero when the server rejects the range,
+    // or answers with a part that does not continue the .part file
+    let mut response = loop {
+        let mut request = client.get(url).header("Accept", "*/*");
+        if resume_from > 0 {
+            tracing::debug!("Resuming from byte {}", resume_from);
+            request = request.header("Range", format!("bytes={}-", resume_from));
+            // Only resume if the file did not change on the server
+            if let Some(etag) = previous.as_ref().and_then(|p| p.etag.as_ref()) {
+                if !etag.starts_with("W/") {
+                    request = request.header("If-Range", etag.as_str());
+                }
+            }
+        }
+
+        let response = tokio::select! {
+            response = request.send() => response.map_err(|e| {
+                tracing::error!("Connection error: {}", e);
+                AppError::network("Connection error", e).with_details(url)
+            })?,
+            _ = cancel.cancelled() => return Err(AppError::cancelled()),
+        };
+
+        let status = response.status();
+        tracing::debug!("Response status: {}", status);
+
+        if status == reqwest::StatusCode::RANGE_NOT_SATISFIABLE && resume_from > 0 {
+            tracing::debug!("Range rejected, restarting download");
+            resume_from = 0;
+            continue;
+        }
+
+        if !status.is_success() {
+            tracing::error!("HTTP error: {}", status);
+            return Err(AppError::http(status).with_details(url));
+        }
+
+        if status == reqwest::StatusCode::PARTIAL_CONTENT {
+            let continues_part = resume_from > 0
+                && content_range_start(&response) == Some(resume_from)
+                && response_etag(&response).is_none_or(|etag| {
+                    previous.as_ref().and_then(|p| p.etag.as_deref()) == Some(etag.as_str())
+                });
+            if !continues_part {
+                if resume_from == 0 {
+                    return Err(AppError::new(
+                        ErrorKind::Network,
+                        "Server sent part of the file when the whole file was asked",
+                    )
+                    .with_details(url));
+                }
+                tracing::debug!(
+                    "Partial content does not continue the .part file, restarting download"
+                );
+                resume_from = 0;
+                continue;
+            }
+        }
+
+        break response;
+    };
+
+    let etag = response_etag(&response);
+    let is_resumed = response.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+    if resume_from > 0 && !is_resumed {
+        tracing::debug!("Server did not resume, restarting download");
+    }
+    let mut downloaded = if is_resumed { resume_from } else { 0 };
+
+    let total_size = response
+        .content_length()
+        .map(|length| downloaded + length)
+        .or_else(|| {
+            previous
+                .as_ref()
+                .and_then(|p| p.total_size)
+                .filter(|_| is_resumed)
+        });
+
+    write_sidecar(
+        &sidecar_path,
+        &PartialDownload {
+            url: url.to_string(),
+            etag,
+            total_size,
+        },
+    )
+    .await?;
+
+    let mut file = if is_resumed {
+        tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(&part_path)
+            .await
+    } else {
+        tokio::fs::File::create(&part_path).await
+    }
+    .map_err(|e| {
+        tracing::error!("Error opening file: {}", e);
+        AppError::io("Error opening file", e).with_details(part_path.display().to_string())
+    })?;
+
+    let total = total_size.unwrap_or(0);
+    let mut last_emit_bytes = downloaded;
+    let mut last_emit_time = std::time::Instant::now();
+    on_progress(downloaded, total);
+
+    // Stream chunks from response to file, the .part file is kept on errors
+    loop {
+        let chunk = tokio::select! {
+            chunk = response.chunk() => chunk,
+            _ = cancel.cancelled() => return Err(AppError::cancelled()),
+        };
+        match chunk {
+            Ok(Some(chunk)) => {
+                file.write_all(&chunk).await.map_err(|e| {
+                    tracing::error!("Error writing chunk: {}", e);
+                    AppError::io("Error writing chunk", e)
+                        .with_details(part_path.display().to_string())
+                })?;
+
+                downloaded += chunk.len() as u64;
+
+                // Emit progress every 100KB or every 500ms
+                if downloaded - last_emit_bytes >= 102_400
+                    || last_emit_time.elapsed().as_millis() >= 500
+                {
+                    on_progress(downloaded, total);
+                    last_emit_bytes = downloaded;
+                    last_emit_time = std::time::Instant::now();
+                }
+            }
+            Ok(None) => {
+                // End of stream
+                break;
+            }
+            Err(e) => {
+                let _ = file.flush().await;
+                tracing::error!("Error reading chunk: {}", e);
+                return Err(AppError::network("Error reading chunk", e).with_details(url));
+            }
+        }
+    }
+
+    file.sync_all().await.map_err(|e| {
+        tracing::error!("Error syncing file: {}", e);
+        AppError::io("Error syncing file", e)
+    })?;
+    drop(file);
+
+    // A truncated transfer is kept as a .part file so the next attempt resumes it
+    let size_on_disk = tokio::fs::metadata(&part_path)
+        .await
+        .map(|m| m.len())
+        .map_err(|e| AppError::io("Error reading downloaded file", e))?;
+    if let Some(expected) = total_size {
+        if downloaded != expected || size_on_disk != expected {
+            tracing::warn!(
+                "Size mismatch: {} bytes on disk, expected {}",
+                size_on_disk,
+                expected
+            );
+            return Err(AppError::new(
+                ErrorKind::Network,
+                format!(
+                    "Download incomplete: received {} of {} bytes",
+                    size_on_disk, expected
+                ),
+            )
+            .with_details(url));
+        }
+    }
+
+    tokio::fs::rename(&part_path, file_path)
+        .await
+        .map_err(|e| AppError::io("Error moving downloaded file", e))?;
+    let _ = tokio::fs::remove_file(&sidecar_path).await;
+
+    on_progress(downloaded, downloaded);
+
+    Ok(downloaded)
+}
+
+/// Size of the `.part` file if its sidecar says it belongs to `url`, 0 otherwise
+async fn resumable_size(url: &str, part_path: &Path, sidecar_path: &Path) -> u64 {
+    let size = tokio::fs::metadata(part_path)
+        .await
+        .map(|m| m.len())
+        .unwrap_or(0);
+    match read_sidecar(sidecar_path).await {
+        Some(sidecar) if sidecar.url == url && size > 0 => size,
+        _ => 0,
+    }
+}
+
+async fn read_sidecar(sidecar_path: &Path) -> Option<PartialDownload> {
+    let content = tokio::fs::read_to_string(sidecar_path).await.ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+async fn write_sidecar(sidecar_path: &Path, sidecar: &PartialDownload) -> Result<(), AppError> {
+    let content = serde_json::to_string_pretty(sidecar).map_err(|e| {
+        AppError::new(
+            ErrorKind::Io,
+            format!("Error serializing download state: {}", e),
+        )
+    })?;
+    tokio::fs::write(sidecar_path, content)
+        .await
+        .map_err(|e| AppError::io("Error writing download state", e))
+}
+
+fn response_etag(response: &reqwest::Response) -> Option<String> {
+    response
+        .headers()
+        .get(reqwest::header::ETAG)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string())
+}
+
+/// Start offset of a `Content-Range: bytes start-end/total` header
+fn content_range_start(response: &reqwest::Response) -> Option<u64> {
+    let value = response
+        .headers()
+        .get(reqwest::header::CONTENT_RANGE)?
+        .to_str()
+        .ok()?;
+    let range = value.trim().strip_prefix("bytes ")?;
+    range.split('-').next()?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    const BODY: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+    /// Minimal HTTP server answering `responses` requests with BODY
+    /// Returns the URL and the Range header of each request
+    struct StandIn {
+        url: String,
+        ranges: Arc<Mutex<Vec<Option<String>>>>,
+    }
+
+    /// `supports_range`: answer Range requests with 206
+    /// `cut_first_at`: close the first connection after sending this many bytes
+    fn serve(responses: usize, supports_range: bool, cut_first_at: Option<usize>) -> StandIn {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/pack.zip", listener.local_addr().unwrap());
+        let ranges = Arc::new(Mutex::new(Vec::new()));
+        let recorded = ranges.clone();
+
+        std::thread::spawn(move || {
+            for index in 0..responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let read = stream.read(&mut buffer).unwrap();
+                    if read == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buffer[..read]);
+                }
+
+                let request = String::from_utf8_lossy(&request).to_lowercase();
+                let range = request
+                    .lines()
+                    .find_map(|line| line.strip_prefix("range: bytes="))
+                    .map(|r| r.trim_end_matches('-').to_string());
+                recorded.lock().unwrap().push(range.clone());
+
+                let start = match range {
+                    Some(start) if supports_range => start.parse::<usize>().unwrap(),
+                    _ => 0,
+                };
+                let mut head = if start > 0 {
+                    format!(
+                        "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {}-{}/{}\r\n",
+                        start,
+                        BODY.len() - 1,
+                        BODY.len()
+                    )
+                } else {
+                    "HTTP/1.1 200 OK\r\n".to_string()
+                };
+                head.push_str(&format!(
+                    "ETag: \"v1\"\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    BODY.len() - start
+                ));
+
+                let body = &BODY[start..];
+                let body = match cut_first_at {
+                    Some(cut) if index == 0 => &body[..cut],
+                    _ => body,
+                };
+                stream.write_all(head.as_bytes()).unwrap();
+                stream.write_all(body).unwrap();
+            }
+        });
+
+        StandIn { url, ranges }
+    }
+
+    #[tokio::test]
+    async fn interrupted_download_is_resumed_with_range() {
+        let server = serve(2, true, Some(10));
+        let dir = TempDir::new("resume-range");
+        let file_path = dir.join("pack.zip");
+        let client = reqwest::Client::new();
+
+        assert!(download_resumable(
+            &client,
+            &server.url,
+            &file_path,
+            &CancelToken::default(),
+            |_, _| {}
+        )
+        .await
+        .is_err());
+        assert_eq!(std::fs::read(part_path(&file_path)).unwrap(), &BODY[..10]);
+        assert!(sidecar_path(&file_path).exists());
+
+        let size = download_resumable(
+            &client,
+            &server.url,
+            &file_path,
+            &CancelToken::default(),
+            |_, _| {},
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(size, BODY.len() as u64);
+        assert_eq!(std::fs::read(&file_path).unwrap(), BODY);
+        assert!(!part_path(&file_path).exists());
+        assert!(!sidecar_path(&file_path).exists());
+        assert_eq!(
+            *server.ranges.lock().unwrap(),
+            vec![None, Some("10".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn restarts_when_server_ignores_range() {
+        let server = serve(2, false, Some(10));
+        let dir = TempDir::new("resume-norange");
+        let file_path = dir.join("pack.zip");
+        let client = reqwest::Client::new();
+
+        assert!(download_resumable(
+            &client,
+            &server.url,
+            &file_path,
+            &CancelToken::default(),
+            |_, _| {}
+        )
+        .await
+        .is_err());
+        download_resumable(
+            &client,
+            &server.url,
+            &file_path,
+            &CancelToken::default(),
+            |_, _| {},
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(std::fs::read(&file_path).unwrap(), BODY);
+        assert_eq!(server.ranges.lock().unwrap()[1], Some("10".to_string()));
+    }
+
+    #[tokio::test]
+    async fn part_file_of_another_url_is_not_resumed() {
+        let server = serve(1, true, None);
+        let dir = TempDir::new("resume-stale");
+        let file_path = dir.join("pack.zip");
+        std::fs::write(part_path(&file_path), b"stale").unwrap();
+        write_sidecar(
+            &sidecar_path(&file_path),
+            &PartialDownload {
+                url: "http://example.invalid/other.zip".to_string(),
+                etag: None,
+                total_size: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        download_resumable(
+            &reqwest::Client::new(),
+            &server.url,
+            &file_path,
+            &CancelToken::default(),
+            |_, _| {},
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(std::fs::read(&file_path).unwrap(), BODY);
+        assert_eq!(*server.ranges.lock().unwrap(), vec![None]);
+    }
+
+    #[tokio::test]
+    async fn part_of_a_changed_file_is_not_appended() {
+        // A weak ETag is never sent as If-Range, so the server answers 206 for the new file
+        let server = serve(2, true, None);
+        let dir = TempDir::new("resume-etag");
+        let file_path = dir.join("pack.zip");
+        std::fs::write(part_path(&file_path), b"old file..").unwrap();
+        write_sidecar(
+            &sidecar_path(&file_path),
+            &PartialDownload {
+                url: server.url.clone(),
+                etag: Some("W/\"v0\"".to_string()),
+                total_size: Some(BODY.len() as u64),
+            },
+        )
+        .await
+        .unwrap();
+
+        let size = download_resumable(
+            &reqwest::Client::new(),
+            &server.url,
+            &file_path,
+            &CancelToken::default(),
+            |_, _| {},
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(size, BODY.len() as u64);
+        assert_eq!(std::fs::read(&file_path).unwrap(), BODY);
+        assert_eq!(
+            *server.ranges.lock().unwrap(),
+            vec![Some("10".to_string()), None]
+        );
+    }
+
+    #[tokio::test]
+    async fn cancelling_stops_a_stalled_download() {
+        // Accepts the connection but never answers
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/pack.zip", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (_stream, _) = listener.accept().unwrap();
+            std::thread::sleep(std::time::Duration::from_secs(5));
+        });
+
+        let cancel = CancelToken::default();
+        let trigger = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            trigger.cancel();
+        });
+
+        let dir = TempDir::new("resume-cancel");
+        let file_path = dir.join("pack.zip");
+        let result = download_resumable(
+            &reqwest::Client::new(),
+            &url,
+            &file_path,
+            &cancel,
+            |_, _| {},
+        )
+        .await;
+
+        assert_eq!(result, Err(AppError::cancelled()));
+    }
+}
@@ -1,5 +1,6 @@
+use super::extract::SkippedEntry;
+use super::utils::{deserialize_f64_from_string, deserialize_optional_f64_from_string, SortField};
 use serde::{Deserialize, Serialize};
-use super::utils::deserialize_f64_from_string;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PacksResponse {
@@ -8,7 +9,7 @@ pub struct PacksResponse {
     pub meta: PackMeta,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Pack {
     pub id: u64,
     pub name: String,
@@ -46,7 +47,56 @@ pub struct Pack {
     pub magnet: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Pack {
+    /// Value a numeric sort field reads, the name sorts separately
+    pub fn field_value(&self, field: SortField) -> f64 {
+        match field {
+            SortField::Name => 0.0,
+            SortField::Popularity => self.play_count as f64,
+            SortField::Overall => self.overall,
+            SortField::Stream => self.stream,
+            SortField::Jumpstream => self.jumpstream,
+            SortField::Handstream => self.handstream,
+            SortField::Jacks => self.jacks,
+            SortField::Chordjacks => self.chordjacks,
+            SortField::Stamina => self.stamina,
+            SortField::Technical => self.technical,
+        }
+    }
+}
+
+#[cfg(test)]
+impl Pack {
+    /// Pack as the API sends it, with every MSD at 0 and the given global tags
+    pub(crate) fn fixture(id: u64, name: &str, tags: &[&str]) -> Pack {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": name,
+            "play_count": 0,
+            "song_count": 40,
+            "banner_path": "",
+            "contains_nsfw": false,
+            "size": "100 MB",
+            "overall": "0",
+            "stream": "0",
+            "jumpstream": "0",
+            "handstream": "0",
+            "jacks": "0",
+            "chordjacks": "0",
+            "stamina": "0",
+            "technical": "0",
+            "tags": tags
+                .iter()
+                .map(|name| serde_json::json!({ "type": "global", "name": name }))
+                .collect::<Vec<_>>(),
+            "download": "",
+            "magnet": ""
+        }))
+        .unwrap()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Tag {
     #[serde(rename = "type")]
     pub tag_type: String,
@@ -65,15 +115,23 @@ pub struct PackLinks {
 pub struct PackMeta {
     #[serde(rename = "current_page")]
     pub current_page: u64,
-    pub from: u64,
+    /// Position of the first and last item of the page, null on an empty page
+    pub from: Option<u64>,
     #[serde(rename = "last_page")]
     pub last_page: u64,
     pub links: Vec<PackMetaLink>,
     pub path: String,
     #[serde(rename = "per_page")]
     pub per_page: u64,
-    pub to: u64,
+    pub to: Option<u64>,
     pub total: u64,
+    /// Packs of the page dropped by filters the API doesn't support
+    #[serde(default)]
+    pub filtered_out: u64,
+    /// Whether filters the API doesn't support were applied to the page,
+    /// `total` and `last_page` then also count the packs they drop
+    #[serde(default)]
+    pub totals_are_estimates: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -83,3 +141,254 @@ pub struct PackMetaLink {
     pub active: bool,
 }
 
+/// Envelope of the API responses holding a single resource
+#[derive(Debug, Clone, Deserialize)]
+pub struct DataResponse<T> {
+    pub data: T,
+}
+
+/// A page of the songs of a pack
+#[derive(Debug, Clone, Deserialize)]
+pub struct PackSongsResponse {
+    pub data: Vec<PackSong>,
+    pub meta: PackMeta,
+}
+
+/// A song of a pack, listed before downloading it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackSong {
+    pub id: u64,
+    #[serde(alias = "name")]
+    pub title: String,
+    #[serde(alias = "author", default)]
+    pub artist: String,
+    #[serde(default, deserialize_with = "deserialize_optional_f64_from_string")]
+    pub bpm: Option<f64>,
+    /// Length of the song in seconds
+    #[serde(
+        alias = "duration",
+        default,
+        deserialize_with = "deserialize_optional_f64_from_string"
+    )]
+    pub length: Option<f64>,
+    #[serde(default)]
+    pub charts: Vec<PackChart>,
+}
+
+/// A chart of a pack song with its difficulty ratings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackChart {
+    #[serde(default)]
+    pub difficulty: String,
+    #[serde(flatten)]
+    pub msd: ChartMsd,
+}
+
+/// MSD of a chart, overall and per skillset
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChartMsd {
+    #[serde(default, deserialize_with = "deserialize_f64_from_string")]
+    pub overall: f64,
+    #[serde(default, deserialize_with = "deserialize_f64_from_string")]
+    pub stream: f64,
+    #[serde(default, deserialize_with = "deserialize_f64_from_string")]
+    pub jumpstream: f64,
+    #[serde(default, deserialize_with = "deserialize_f64_from_string")]
+    pub handstream: f64,
+    #[serde(default, deserialize_with = "deserialize_f64_from_string")]
+    pub jacks: f64,
+    #[serde(default, deserialize_with = "deserialize_f64_from_string")]
+    pub chordjacks: f64,
+    #[serde(default, deserialize_with = "deserialize_f64_from_string")]
+    pub stamina: f64,
+    #[serde(default, deserialize_with = "deserialize_f64_from_string")]
+    pub technical: f64,
+}
+
+/// A pack with its song list, returned by `fetch_pack_details`
+#[derive(Debug, Clone, Serialize)]
+pub struct PackDetails {
+    pub pack: Pack,
+    pub songs: Vec<PackSong>,
+}
+
+/// Stage of a pack download, as reported to the frontend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadStage {
+    Queued,
+    Downloading,
+    Paused,
+    Verifying,
+    Extracting,
+    Converting,
+    Cancelled,
+}
+
+/// Payload of the `download-progress-{pack_id}` events
+/// `downloaded`/`total` count bytes while downloading, entries while extracting and songs while converting
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadProgress {
+    pub pack_id: u64,
+    pub stage: DownloadStage,
+    pub downloaded: u64,
+    pub total: u64,
+    /// Bytes written so far while extracting
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<u64>,
+    /// Title of the song being converted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current: Option<String>,
+}
+
+impl DownloadProgress {
+    pub fn new(pack_id: u64, stage: DownloadStage, downloaded: u64, total: u64) -> Self {
+        DownloadProgress {
+            pack_id,
+            stage,
+            downloaded,
+            total,
+            bytes: None,
+            current: None,
+        }
+    }
+}
+
+/// What went wrong with a simfile that was not (fully) converted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConversionFailureReason {
+    /// The simfile could not be parsed or has no supported chart
+    Decode,
+    /// A chart could not be written as .osu
+    Encode,
+    /// The rated audio could not be rendered
+    Audio,
+    /// Reading the simfile or writing a .osu file failed
+    Io,
+}
+
+/// A simfile, or part of it, that could not be converted
+#[derive(Debug, Clone, Serialize)]
+pub struct ConversionFailure {
+    pub file: String,
+    pub reason: ConversionFailureReason,
+    pub message: String,
+}
+
+/// How a downloaded archive compares to the one the pack was last installed from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveStatus {
+    /// The pack was not installed, or its archive was not hashed
+    #[default]
+    New,
+    Identical,
+    Changed,
+}
+
+impl ArchiveStatus {
+    /// Compares the SHA-256 of the archive with the one recorded at the last install
+    pub fn compare(previous_sha256: Option<&str>, sha256: &str) -> Self {
+        match previous_sha256 {
+            Some(previous) if previous == sha256 => ArchiveStatus::Identical,
+            Some(_) => ArchiveStatus::Changed,
+            None => ArchiveStatus::New,
+        }
+    }
+}
+
+/// A song folder that could not be installed under its own name, the song path already has a folder of that name
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SongFolderConflict {
+    pub name: String,
+    /// Name the folder was installed under, `None` when it was not installed
+    pub installed_as: Option<String>,
+}
+
+/// Outcome of a pack installation, returned by `download_pack`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct InstallReport {
+    pub archive_path: String,
+    pub archive_status: ArchiveStatus,
+    /// Simfiles found in the pack
+    pub songs_found: usize,
+    /// Simfiles that produced at least one .osu file
+    pub songs_converted: usize,
+    /// Charts converted at 1.0x
+    pub charts_converted: usize,
+    /// .osu files written, rated charts included
+    pub files_written: usize,
+    pub failures: Vec<ConversionFailure>,
+    /// Archive entries that were not extracted, such as symlinks or paths escaping the pack
+    pub skipped_entries: Vec<SkippedEntry>,
+    /// Song folders renamed or left out to keep folders the pack did not install
+    pub song_folder_conflicts: Vec<SongFolderConflict>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pack_songs_accept_api_field_names() {
+        let song: PackSong = serde_json::from_value(serde_json::json!({
+            "id": 12,
+            "name": "Song",
+            "author": "Artist",
+            "bpm": "175.5",
+            "length": null,
+            "charts": [
+                { "difficulty": "Challenge", "overall": "27.31", "stream": 25, "jacks": "18.2" }
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(song.title, "Song");
+        assert_eq!(song.artist, "Artist");
+        assert_eq!(song.bpm, Some(175.5));
+        assert_eq!(song.length, None);
+        assert_eq!(song.charts[0].msd.overall, 27.31);
+        assert_eq!(song.charts[0].msd.stream, 25.0);
+        assert_eq!(song.charts[0].msd.technical, 0.0);
+    }
+
+    #[test]
+    fn empty_song_pages_are_read() {
+        let response: PackSongsResponse = serde_json::from_value(serde_json::json!({
+            "data": [],
+            "meta": {
+                "current_page": 1,
+                "from": null,
+                "last_page": 1,
+                "links": [],
+                "path": "https://api.etternaonline.com/api/packs/1/songs",
+                "per_page": 25,
+                "to": null,
+                "total": 0
+            }
+        }))
+        .unwrap();
+
+        assert!(response.data.is_empty());
+        assert_eq!((response.meta.from, response.meta.to), (None, None));
+    }
+
+    #[test]
+    fn archives_are_compared_with_the_installed_one() {
+        assert_eq!(ArchiveStatus::compare(None, "abc"), ArchiveStatus::New);
+        assert_eq!(
+            ArchiveStatus::compare(Some("abc"), "abc"),
+            ArchiveStatus::Identical
+        );
+        assert_eq!(
+            ArchiveStatus::compare(Some("abd"), "abc"),
+            ArchiveStatus::Changed
+        );
+        assert_eq!(
+            serde_json::to_value(ArchiveStatus::Identical).unwrap(),
+            "identical"
+        );
+    }
+}
@@ -0,0 +1,333 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::error::{AppError, ErrorKind};
+use crate::library::{Library, LibraryPack};
+use crate::maps::collection::{beatmap_hash, collection_db_path, CollectionDb};
+
+/// What an uninstall removed, and what it left alone
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UninstallReport {
+    pub removed: Vec<String>,
+    /// Paths that were kept, with the reason
+    pub skipped: Vec<String>,
+    pub collection_removed: bool,
+}
+
+/// Removes an installed pack: its song folders, generated .osu files, archive,
+/// extracted tree and osu! collection, then drops it from the library
+#[tauri::command]
+#[tracing::instrument]
+pub fn uninstall_pack(pack_id: u64) -> Result<UninstallReport, AppError> {
+    tracing::info!("Uninstalling pack {}", pack_id);
+
+    let library = Library::load()?;
+    let pack = library.get(pack_id).cloned().ok_or_else(|| {
+        AppError::new(
+            ErrorKind::NotFound,
+            format!("Pack {} is not installed", pack_id),
+        )
+    })?;
+    let other_packs: Vec<LibraryPack> = library
+        .packs
+        .into_iter()
+        .filter(|other| other.id != pack_id)
+        .collect();
+
+    let downloads_dir = super::download::get_downloads_dir()?;
+    let mut report = remove_pack_files(&pack, &other_packs, &downloads_dir);
+
+    if let Some(collection_name) = &pack.collection_name {
+        match remove_collection(&pack, collection_name) {
+            Ok(removed) => report.collection_removed = removed,
+            Err(e) => tracing::warn!("Error removing osu! collection: {}", e),
+        }
+    }
+
+    Library::update(|library| library.remove(pack_id))?;
+
+    for skipped in &report.skipped {
+        tracing::info!("Kept {}", skipped);
+    }
+    tracing::info!(
+        "Removed {} paths, kept {}",
+        report.removed.len(),
+        report.skipped.len()
+    );
+
+    Ok(report)
+}
+
+/// Deletes the files recorded for a pack
+/// Only paths inside the directories the pack was installed to are touched, song folders
+/// shared with another installed pack are kept, and .osu files edited since are kept
+/// A song folder is only removed once it is empty, so files added by the user stay
+fn remove_pack_files(
+    pack: &LibraryPack,
+    other_packs: &[LibraryPack],
+    downloads_dir: &Path,
+) -> UninstallReport {
+    let mut report = UninstallReport::default();
+    let install_path = PathBuf::from(&pack.install_path);
+    let extract_path = PathBuf::from(&pack.extract_path);
+
+    // Decide which song folders are ours before any file is removed
+    let mut song_folders = Vec::new();
+    let mut kept_folders = Vec::new();
+    for folder in &pack.song_folders {
+        let folder_path = PathBuf::from(folder);
+        // Songs that were never copied go away with the extracted tree
+        if !folder_path.exists() || folder_path.starts_with(&extract_path) {
+            continue;
+        }
+        if folder_path.parent() != Some(install_path.as_path()) {
+            report
+                .skipped
+                .push(format!("{} (outside {})", folder, pack.install_path));
+            kept_folders.push(folder_path);
+        } else if other_packs
+            .iter()
+            .any(|other| other.song_folders.contains(folder))
+        {
+            report
+                .skipped
+                .push(format!("{} (used by another pack)", folder));
+            kept_folders.push(folder_path);
+        } else {
+            song_folders.push(folder_path);
+        }
+    }
+    let in_song_folder = |path: &Path| song_folders.iter().any(|f| path.starts_with(f));
+
+    for osu in &pack.osu_files {
+        let osu_path = Path::new(&osu.path);
+        if !osu_path.exists()
+            || osu_path.starts_with(&extract_path)
+            || kept_folders.iter().any(|f| osu_path.starts_with(f))
+        {
+            continue;
+        }
+        if other_packs
+            .iter()
+            .any(|other| other.osu_files.iter().any(|o| o.path == osu.path))
+        {
+            report
+                .skipped
+                .push(format!("{} (used by another pack)", osu.path));
+        } else if !hash_matches(&osu.path, &osu.hash) {
+            report.skipped.push(format!("{} (modified)", osu.path));
+        } else if in_song_folder(osu_path) {
+            // Reported with its folder
+            remove_file_quietly(osu_path, &mut report);
+        } else {
+            remove_path(osu_path, &mut report);
+        }
+    }
+
+    for file in &pack.song_files {
+        let path = Path::new(file);
+        if path.is_file() && in_song_folder(path) {
+            remove_file_quietly(path, &mut report);
+        }
+    }
+
+    for folder in &song_folders {
+        if remove_empty_dirs(folder) {
+            report.removed.push(folder.to_string_lossy().to_string());
+        } else {
+            report.skipped.push(format!(
+                "{} (contains files not installed by this pack)",
+                folder.display()
+            ));
+        }
+    }
+
+    // The archive and extracted tree always live in the downloads directory
+    for path in [&pack.archive_path, &pack.extract_path] {
+        let path = Path::new(path);
+        if !path.exists() {
+            continue;
+        }
+        if is_inside(path, downloads_dir) {
+            remove_path(path, &mut report);
+        } else {
+            report.skipped.push(format!(
+                "{} (outside {})",
+                path.display(),
+                downloads_dir.display()
+            ));
+        }
+    }
+
+    report
+}
+
+/// Removes the pack's osu! collection, unless maps were added to it by hand
+fn remove_collection(pack: &LibraryPack, collection_name: &str) -> Result<bool, AppError> {
+    let Some(db_path) = collection_db_path(&pack.install_path) else {
+        return Ok(false);
+    };
+
+    let mut db = CollectionDb::load(&db_path)?;
+    let Some(collection) = db.collections.iter().find(|c| c.name == collection_name) else {
+        return Ok(false);
+    };
+
+    let is_ours = collection
+        .beatmap_hashes
+        .iter()
+        .all(|hash| pack.osu_files.iter().any(|osu| &osu.hash == hash));
+    if !is_ours {
+        tracing::info!(
+            "Collection {} contains other maps, keeping it",
+            collection_name
+        );
+        return Ok(false);
+    }
+
+    db.remove(collection_name);
+    db.save(&db_path)?;
+
+    Ok(true)
+}
+
+fn hash_matches(path: &str, hash: &str) -> bool {
+    std::fs::read(path)
+        .map(|content| beatmap_hash(&content) == hash)
+        .unwrap_or(false)
+}
+
+/// Whether `path` is strictly inside `root`, after resolving symlinks and `..`
+fn is_inside(path: &Path, root: &Path) -> bool {
+    match (path.canonicalize(), root.canonicalize()) {
+        (Ok(path), Ok(root)) => path != root && path.starts_with(root),
+        _ => false,
+    }
+}
+
+fn remove_file_quietly(path: &Path, report: &mut UninstallReport) {
+    if let Err(e) = std::fs::remove_file(path) {
+        report.skipped.push(format!("{} ({})", path.display(), e));
+    }
+}
+
+/// Removes a directory and its subdirectories as long as they hold no file
+/// Returns whether the directory itself was removed
+fn remove_empty_dirs(dir: &Path) -> bool {
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+                remove_empty_dirs(&entry.path());
+            }
+        }
+    }
+    std::fs::remove_dir(dir).is_ok()
+}
+
+fn remove_path(path: &Path, report: &mut UninstallReport) {
+    let result = if path.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    };
+
+    match result {
+        Ok(()) => report.removed.push(path.to_string_lossy().to_string()),
+        Err(e) => report.skipped.push(format!("{} ({})", path.display(), e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::LibraryOsuFile;
+    use crate::test_utils::TempDir;
+
+    fn write_osu(path: &Path, content: &[u8]) -> LibraryOsuFile {
+        std::fs::write(path, content).unwrap();
+        LibraryOsuFile {
+            path: path.to_string_lossy().to_string(),
+            hash: beatmap_hash(content),
+        }
+    }
+
+    #[test]
+    fn only_removes_files_created_by_the_pack() {
+        let root = TempDir::new("uninstall");
+        let downloads = root.join("downloads");
+        let songs = root.join("Songs");
+        let extract = downloads.join("Pack");
+        for dir in [
+            &extract,
+            &songs.join("Ours").join("Backgrounds"),
+            &songs.join("Edited"),
+            &songs.join("Mixed"),
+            &songs.join("Shared"),
+        ] {
+            std::fs::create_dir_all(dir).unwrap();
+        }
+        let archive = downloads.join("Pack.zip");
+        std::fs::write(&archive, b"zip").unwrap();
+        let song_files: Vec<String> = [
+            songs.join("Ours").join("song.ogg"),
+            songs.join("Ours").join("Backgrounds").join("bg.png"),
+            songs.join("Mixed").join("song.ogg"),
+        ]
+        .iter()
+        .map(|path| {
+            std::fs::write(path, b"copied").unwrap();
+            path.to_string_lossy().to_string()
+        })
+        .collect();
+
+        let ours = write_osu(&songs.join("Ours").join("Hard.osu"), b"ours");
+        let edited = write_osu(&songs.join("Edited").join("Hard.osu"), b"original");
+        std::fs::write(&edited.path, b"edited by the user").unwrap();
+        let mixed = write_osu(&songs.join("Mixed").join("Hard.osu"), b"mixed");
+        // Added by the user next to the pack's files
+        std::fs::write(songs.join("Mixed").join("Mine.osu"), b"mine").unwrap();
+        let shared = write_osu(&songs.join("Shared").join("Hard.osu"), b"shared");
+
+        let folder = |name: &str| songs.join(name).to_string_lossy().to_string();
+        let pack = LibraryPack {
+            id: 1,
+            name: "Pack".to_string(),
+            archive_path: archive.to_string_lossy().to_string(),
+            archive_sha256: None,
+            extract_path: extract.to_string_lossy().to_string(),
+            install_path: songs.to_string_lossy().to_string(),
+            song_folders: vec![
+                folder("Ours"),
+                folder("Edited"),
+                folder("Mixed"),
+                folder("Shared"),
+            ],
+            song_files,
+            osu_files: vec![ours, edited, mixed, shared],
+            collection_name: None,
+            installed_at: 0,
+            updated_at: 0,
+        };
+        let other = LibraryPack {
+            id: 2,
+            song_folders: vec![folder("Shared")],
+            osu_files: vec![pack.osu_files[3].clone()],
+            ..pack.clone()
+        };
+
+        let report = remove_pack_files(&pack, &[other], &downloads);
+
+        assert!(!songs.join("Ours").exists());
+        assert!(songs.join("Edited").join("Hard.osu").exists());
+        assert!(!songs.join("Mixed").join("Hard.osu").exists());
+        assert!(!songs.join("Mixed").join("song.ogg").exists());
+        assert!(songs.join("Mixed").join("Mine.osu").exists());
+        assert!(songs.join("Shared").join("Hard.osu").exists());
+        assert!(!archive.exists());
+        assert!(!extract.exists());
+        // Ours, the archive and the extracted tree
+        assert_eq!(report.removed.len(), 3, "{:?}", report);
+        // The edited chart, the Edited and Mixed folders, and the shared folder
+        assert_eq!(report.skipped.len(), 4, "{:?}", report);
+    }
+}
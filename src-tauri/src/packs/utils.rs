@@ -1,4 +1,9 @@
-use serde::Deserializer;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+use super::types::Pack;
+use crate::error::{AppError, ErrorKind};
 
 pub fn deserialize_f64_from_string<'de, D>(deserializer: D) -> Result<f64, D::Error>
 where
@@ -42,7 +47,10 @@ where
             E: serde::de::Error,
         {
             value.parse::<f64>().map_err(|_| {
-                E::invalid_value(serde::de::Unexpected::Str(value), &"a string representing a number")
+                E::invalid_value(
+                    serde::de::Unexpected::Str(value),
+                    &"a string representing a number",
+                )
             })
         }
     }
@@ -50,58 +58,298 @@ where
     deserializer.deserialize_any(F64Visitor)
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum SortField {
-    Name,
-    Popularity,
-    Overall,
-    Stream,
-    Jumpstream,
-    Handstream,
-    Jacks,
-    Chordjacks,
-    Stamina,
-    Technical,
+/// Like `deserialize_f64_from_string`, null or a missing field (with `#[serde(default)]`) gives `None`
+pub fn deserialize_optional_f64_from_string<'de, D>(
+    deserializer: D,
+) -> Result<Option<f64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Number(#[serde(deserialize_with = "deserialize_f64_from_string")] f64);
+
+    let number: Option<Number> = Option::deserialize(deserializer)?;
+    Ok(number.map(|Number(value)| value))
+}
+
+/// Declares `SortField` from one table of (variant, name used by the API and the frontend, label)
+macro_rules! sort_fields {
+    ($($variant:ident => $name:literal, $label:literal;)*) => {
+        /// Field the pack list can be sorted on, also used for the MSD filters
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum SortField {
+            $($variant,)*
+        }
+
+        impl SortField {
+            pub const ALL: [SortField; [$($name),*].len()] = [$(SortField::$variant),*];
+
+            /// Name used by the API and the frontend
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $(SortField::$variant => $name,)*
+                }
+            }
+
+            pub fn label(&self) -> &'static str {
+                match self {
+                    $(SortField::$variant => $label,)*
+                }
+            }
+        }
+    };
+}
+
+sort_fields! {
+    Name => "name", "Name";
+    Popularity => "popularity", "Popularity";
+    Overall => "overall", "Overall";
+    Stream => "stream", "Stream";
+    Jumpstream => "jumpstream", "Jumpstream";
+    Handstream => "handstream", "Handstream";
+    Jacks => "jacks", "Jacks";
+    Chordjacks => "chordjacks", "Chordjacks";
+    Stamina => "stamina", "Stamina";
+    Technical => "technical", "Technical";
 }
 
 impl SortField {
+    /// Whether the field is an MSD, overall or a skillset
+    pub fn is_msd(&self) -> bool {
+        !matches!(self, SortField::Name | SortField::Popularity)
+    }
+
+    /// Names of the given fields, for error messages
+    pub fn list(fields: impl Iterator<Item = SortField>) -> String {
+        fields
+            .map(|field| field.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl std::fmt::Display for SortField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for SortField {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for SortField {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse()
+            .map_err(|e: AppError| serde::de::Error::custom(e.message))
+    }
+}
+
+impl FromStr for SortField {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SortField::ALL
+            .into_iter()
+            .find(|field| field.as_str() == s)
+            .ok_or_else(|| {
+                AppError::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "Invalid sort field: {}. Valid options: {}",
+                        s,
+                        SortField::list(SortField::ALL.into_iter())
+                    ),
+                )
+            })
+    }
+}
+
+/// Direction of a sort key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    Ascending,
+    Descending,
+}
+
+impl SortDirection {
+    pub const ALL: [SortDirection; 2] = [SortDirection::Ascending, SortDirection::Descending];
+
+    /// Name used by the frontend
     pub fn as_str(&self) -> &'static str {
         match self {
-            SortField::Name => "name",
-            SortField::Popularity => "popularity",
-            SortField::Overall => "overall",
-            SortField::Stream => "stream",
-            SortField::Jumpstream => "jumpstream",
-            SortField::Handstream => "handstream",
-            SortField::Jacks => "jacks",
-            SortField::Chordjacks => "chordjacks",
-            SortField::Stamina => "stamina",
-            SortField::Technical => "technical",
+            SortDirection::Ascending => "ascending",
+            SortDirection::Descending => "descending",
         }
     }
-    
-    pub fn from_str(s: &str) -> Option<Self> {
-        match s {
-            "name" => Some(SortField::Name),
-            "popularity" => Some(SortField::Popularity),
-            "overall" => Some(SortField::Overall),
-            "stream" => Some(SortField::Stream),
-            "jumpstream" => Some(SortField::Jumpstream),
-            "handstream" => Some(SortField::Handstream),
-            "jacks" => Some(SortField::Jacks),
-            "chordjacks" => Some(SortField::Chordjacks),
-            "stamina" => Some(SortField::Stamina),
-            "technical" => Some(SortField::Technical),
-            _ => None,
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SortDirection::Ascending => "Ascending",
+            SortDirection::Descending => "Descending",
         }
     }
 }
 
-pub fn build_sort_string(field: SortField, descending: bool) -> String {
-    if descending {
-        format!("-{}", field.as_str())
-    } else {
-        field.as_str().to_string()
+/// One key of a sort, sent by the frontend as `{ field, direction }`
+/// and to the API as `field` or `-field` when descending
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SortKey {
+    pub field: SortField,
+    pub direction: SortDirection,
+}
+
+impl SortKey {
+    pub fn new(field: SortField, direction: SortDirection) -> Self {
+        SortKey { field, direction }
+    }
+}
+
+impl std::fmt::Display for SortKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.direction == SortDirection::Descending {
+            f.write_str("-")?;
+        }
+        f.write_str(self.field.as_str())
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s.strip_prefix('-') {
+            Some(field) => Ok(SortKey::new(field.parse()?, SortDirection::Descending)),
+            None => Ok(SortKey::new(s.parse()?, SortDirection::Ascending)),
+        }
+    }
+}
+
+/// Keys applied in order, the next one breaking ties of the previous, written `-overall,name`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sort(pub Vec<SortKey>);
+
+impl Sort {
+    /// Checks there is at least one key and that no field is used twice
+    pub fn new(keys: Vec<SortKey>) -> Result<Self, AppError> {
+        if keys.is_empty() {
+            return Err(AppError::new(
+                ErrorKind::InvalidInput,
+                "Sort can't be empty",
+            ));
+        }
+        for (index, key) in keys.iter().enumerate() {
+            if keys[..index].iter().any(|other| other.field == key.field) {
+                return Err(AppError::new(
+                    ErrorKind::InvalidInput,
+                    format!("Sort field {} is used twice", key.field),
+                ));
+            }
+        }
+
+        Ok(Sort(keys))
+    }
+
+    /// Orders two packs like the API does for this sort
+    pub fn compare(&self, a: &Pack, b: &Pack) -> Ordering {
+        self.0
+            .iter()
+            .map(|key| {
+                let order = match key.field {
+                    SortField::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+                    field => a.field_value(field).total_cmp(&b.field_value(field)),
+                };
+                match key.direction {
+                    SortDirection::Ascending => order,
+                    SortDirection::Descending => order.reverse(),
+                }
+            })
+            .find(|order| order.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl std::fmt::Display for Sort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let keys: Vec<String> = self.0.iter().map(|key| key.to_string()).collect();
+        f.write_str(&keys.join(","))
+    }
+}
+
+impl FromStr for Sort {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let keys = s
+            .split(',')
+            .filter(|key| !key.trim().is_empty())
+            .map(|key| key.parse())
+            .collect::<Result<Vec<SortKey>, AppError>>()?;
+
+        Sort::new(keys)
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multi_key_sorts_round_trip() {
+        let sort: Sort = " -overall, name ".parse().unwrap();
+        assert_eq!(
+            sort,
+            Sort(vec![
+                SortKey::new(SortField::Overall, SortDirection::Descending),
+                SortKey::new(SortField::Name, SortDirection::Ascending),
+            ])
+        );
+        assert_eq!(sort.to_string(), "-overall,name");
+
+        for field in SortField::ALL {
+            assert_eq!(field.as_str().parse::<SortField>().unwrap(), field);
+            assert_eq!(serde_json::to_value(field).unwrap(), field.as_str());
+            assert_eq!(
+                serde_json::from_value::<SortField>(field.as_str().into()).unwrap(),
+                field
+            );
+        }
+        for direction in SortDirection::ALL {
+            assert_eq!(serde_json::to_value(direction).unwrap(), direction.as_str());
+            assert_eq!(
+                serde_json::from_value::<SortDirection>(direction.as_str().into()).unwrap(),
+                direction
+            );
+        }
+
+        // The frontend sends the keys as objects
+        let keys: Vec<SortKey> = serde_json::from_value(serde_json::json!([
+            { "field": "overall", "direction": "descending" },
+            { "field": "name", "direction": "ascending" }
+        ]))
+        .unwrap();
+        assert_eq!(Sort::new(keys).unwrap(), sort);
+    }
+
+    #[test]
+    fn invalid_sorts_are_refused() {
+        let error = "-difficulty".parse::<Sort>().unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidInput);
+        assert!(error
+            .message
+            .starts_with("Invalid sort field: difficulty. Valid options: name, popularity"));
+
+        assert!("overall,-overall".parse::<Sort>().is_err());
+        assert!(",".parse::<Sort>().is_err());
+        assert!(Sort::new(Vec::new()).is_err());
+        assert!(serde_json::from_value::<SortField>("difficulty".into())
+            .unwrap_err()
+            .to_string()
+            .starts_with("Invalid sort field: difficulty"));
+    }
+}
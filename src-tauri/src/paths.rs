@@ -0,0 +1,150 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tauri::Manager;
+
+use crate::error::{AppError, ErrorKind};
+
+/// Platform directories of the app, resolved once at startup
+struct AppDirs {
+    config: PathBuf,
+    data: PathBuf,
+    cache: PathBuf,
+}
+
+static APP_DIRS: OnceLock<AppDirs> = OnceLock::new();
+
+/// Resolves the app directories and moves over the files of older versions,
+/// which kept them next to the working directory
+pub fn init(app: &tauri::AppHandle) -> Result<(), AppError> {
+    let resolve_error = |e: tauri::Error| {
+        AppError::new(ErrorKind::Io, format!("Error getting app directory: {}", e))
+    };
+    let dirs = AppDirs {
+        config: app.path().app_config_dir().map_err(resolve_error)?,
+        data: app.path().app_data_dir().map_err(resolve_error)?,
+        cache: app.path().app_cache_dir().map_err(resolve_error)?,
+    };
+    let _ = APP_DIRS.set(dirs);
+
+    // Logging is not set up yet, and a broken legacy install must not keep the app from starting
+    if let Ok(cwd) = std::env::current_dir() {
+        match migrate_legacy_config(&cwd.join("config"), &config_dir()?) {
+            Ok(true) => {
+                if let Err(e) = keep_legacy_downloads(&cwd.join("downloads")) {
+                    eprintln!("Legacy downloads not kept: {}", e);
+                }
+            }
+            Ok(false) => {}
+            Err(e) => eprintln!("Legacy config not migrated: {}", e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Directory holding settings.json and library.json
+pub fn config_dir() -> Result<PathBuf, AppError> {
+    ensure_dir(app_dirs().config.clone())
+}
+
+/// Directory holding the downloaded packs by default
+pub fn data_dir() -> Result<PathBuf, AppError> {
+    ensure_dir(app_dirs().data.clone())
+}
+
+/// Directory holding data that can be fetched again, such as the pack catalog
+pub fn cache_dir() -> Result<PathBuf, AppError> {
+    ensure_dir(app_dirs().cache.clone())
+}
+
+/// Before `init` (in tests) everything goes to a temporary directory
+fn app_dirs() -> &'static AppDirs {
+    APP_DIRS.get_or_init(|| {
+        let root = std::env::temp_dir().join("rotterna");
+        AppDirs {
+            config: root.join("config"),
+            data: root.join("data"),
+            cache: root.join("cache"),
+        }
+    })
+}
+
+fn ensure_dir(dir: PathBuf) -> Result<PathBuf, AppError> {
+    std::fs::create_dir_all(&dir).map_err(|e| {
+        AppError::io("Error creating app directory", e).with_details(dir.display().to_string())
+    })?;
+    Ok(dir)
+}
+
+/// Copies the files of a `./config` directory once, while the new one has no settings yet
+/// The old files are left in place, returns whether anything was copied
+fn migrate_legacy_config(legacy_dir: &Path, config_dir: &Path) -> Result<bool, AppError> {
+    if !legacy_dir.join("settings.json").exists() || config_dir.join("settings.json").exists() {
+        return Ok(false);
+    }
+
+    let entries = std::fs::read_dir(legacy_dir)
+        .map_err(|e| AppError::io("Error reading legacy config directory", e))?;
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let source = entry.path();
+        if !source.is_file() {
+            continue;
+        }
+        let target = config_dir.join(entry.file_name());
+        std::fs::copy(&source, &target)
+            .map_err(|e| AppError::io(format!("Error migrating {}", source.display()), e))?;
+    }
+
+    tracing::info!(
+        "Migrated config from {} to {}",
+        legacy_dir.display(),
+        config_dir.display()
+    );
+    Ok(true)
+}
+
+/// Packs installed by an older version stay in `./downloads`, so they can still be uninstalled
+fn keep_legacy_downloads(legacy_dir: &Path) -> Result<(), AppError> {
+    if !legacy_dir.is_dir() {
+        return Ok(());
+    }
+
+    let mut settings = crate::settings::Settings::load()?;
+    if settings.downloads_path.is_empty() {
+        settings.downloads_path = legacy_dir.to_string_lossy().to_string();
+        settings.save()?;
+        tracing::info!("Keeping downloads in {}", legacy_dir.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    #[test]
+    fn legacy_config_is_migrated_once() {
+        let root = TempDir::new("migrate");
+        let legacy_dir = root.join("legacy");
+        let config_dir = root.join("config");
+        std::fs::create_dir_all(&legacy_dir).unwrap();
+        std::fs::create_dir_all(&config_dir).unwrap();
+        std::fs::write(legacy_dir.join("settings.json"), "old settings").unwrap();
+        std::fs::write(legacy_dir.join("library.json"), "old library").unwrap();
+
+        assert!(migrate_legacy_config(&legacy_dir, &config_dir).unwrap());
+        assert_eq!(
+            std::fs::read_to_string(config_dir.join("library.json")).unwrap(),
+            "old library"
+        );
+
+        // Settings saved since are never overwritten
+        std::fs::write(config_dir.join("settings.json"), "new settings").unwrap();
+        assert!(!migrate_legacy_config(&legacy_dir, &config_dir).unwrap());
+        assert_eq!(
+            std::fs::read_to_string(config_dir.join("settings.json")).unwrap(),
+            "new settings"
+        );
+    }
+}
@@ -1,38 +1,107 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+use crate::error::{AppError, ErrorKind, FieldError};
+use crate::logging::LogLevel;
+use crate::maps::rates::{rate_count, MAX_RATE_COUNT, RATE_RANGE};
+use crate::packs::client::ClientConfig;
+use crate::packs::extract::ExtractLimits;
+
+/// Version written to settings.json, bumped whenever a migration step is added
+const SETTINGS_VERSION: u32 = 1;
+
+/// Steps upgrading a settings file by one version, the step at index N reads version N
+const MIGRATIONS: [fn(&mut serde_json::Map<String, serde_json::Value>); SETTINGS_VERSION as usize] =
+    [from_unversioned];
+
+/// Missing fields take their default value, so files written by older versions still load
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Settings {
+    /// Schema version of the file the settings were read from
+    pub version: u32,
     pub hp_drain_rate: f64,
     pub overall_difficulty: f64,
     pub song_path: String,
+    /// Where packs are downloaded and extracted, empty uses the app data directory
+    pub downloads_path: String,
+    /// Slowest rate generated on download
+    pub rate_min: f64,
+    /// Fastest rate generated on download
+    pub rate_max: f64,
+    pub rate_step: f64,
+    /// ffmpeg executable used to render rated audio
+    pub ffmpeg_path: String,
+    /// Packs downloaded at the same time, the others wait in the queue
+    pub max_concurrent_downloads: usize,
+    /// Simfiles converted at the same time, 0 uses one thread per CPU core
+    pub conversion_threads: usize,
+    /// Limits applied when extracting pack archives
+    pub extract_limits: ExtractLimits,
+    /// Verbosity of the log file
+    pub log_level: LogLevel,
+    /// Root of the EtternaOnline API, empty uses the public one, applied on restart
+    pub api_url: String,
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Settings {
+            version: SETTINGS_VERSION,
             hp_drain_rate: 8.0,
             overall_difficulty: 9.0,
             song_path: String::new(),
+            downloads_path: String::new(),
+            rate_min: 1.0,
+            rate_max: 1.0,
+            rate_step: 0.1,
+            ffmpeg_path: "ffmpeg".to_string(),
+            max_concurrent_downloads: 2,
+            conversion_threads: 0,
+            extract_limits: ExtractLimits::default(),
+            log_level: LogLevel::default(),
+            api_url: String::new(),
         }
     }
 }
 
 impl Settings {
-    fn get_config_path() -> Result<PathBuf, String> {
-        let mut config_dir = std::env::current_dir()
-            .map_err(|e| format!("Error getting current directory: {}", e))?;
-        config_dir.push("config");
-        std::fs::create_dir_all(&config_dir)
-            .map_err(|e| format!("Error creating config directory: {}", e))?;
-        config_dir.push("settings.json");
-        Ok(config_dir)
+    /// Rates to generate on download, besides 1.0x
+    pub fn rates(&self) -> Vec<f64> {
+        crate::maps::rates::rates_in_range(self.rate_min, self.rate_max, self.rate_step)
+    }
+
+    /// Connection settings of the EtternaOnline client
+    pub fn client_config(&self) -> ClientConfig {
+        let mut config = ClientConfig::default();
+        if !self.api_url.is_empty() {
+            config.base_url = self.api_url.clone();
+        }
+        config
+    }
+
+    /// Directory packs are downloaded to, created if it doesn't exist
+    pub fn downloads_dir(&self) -> Result<PathBuf, AppError> {
+        let downloads_dir = if self.downloads_path.is_empty() {
+            crate::paths::data_dir()?.join("downloads")
+        } else {
+            PathBuf::from(&self.downloads_path)
+        };
+        fs::create_dir_all(&downloads_dir).map_err(|e| {
+            AppError::io("Error creating downloads directory", e)
+                .with_details(downloads_dir.display().to_string())
+        })?;
+        Ok(downloads_dir)
+    }
+
+    fn get_config_path() -> Result<PathBuf, AppError> {
+        Ok(crate::paths::config_dir()?.join("settings.json"))
     }
 
-    pub fn load() -> Result<Settings, String> {
+    pub fn load() -> Result<Settings, AppError> {
         let config_path = Self::get_config_path()?;
-        
+
         if !config_path.exists() {
             let default = Settings::default();
             default.save()?;
@@ -40,33 +109,246 @@ impl Settings {
         }
 
         let content = fs::read_to_string(&config_path)
-            .map_err(|e| format!("Error reading settings file: {}", e))?;
-        
-        let settings: Settings = serde_json::from_str(&content)
-            .map_err(|e| format!("Error parsing settings file: {}", e))?;
-        
+            .map_err(|e| AppError::io("Error reading settings file", e))?;
+
+        let parse_error = |e: serde_json::Error| {
+            AppError::new(
+                ErrorKind::Settings,
+                format!("Error parsing settings file: {}", e),
+            )
+            .with_details(config_path.display().to_string())
+        };
+        let mut value: serde_json::Value = serde_json::from_str(&content).map_err(parse_error)?;
+        let migrated = migrate(&mut value);
+        let settings: Settings = serde_json::from_value(value).map_err(parse_error)?;
+
+        // Write the upgraded file so the migration only runs once
+        if migrated {
+            settings.save()?;
+        }
+
         Ok(settings)
     }
 
-    pub fn save(&self) -> Result<(), String> {
+    /// Reads the settings, falling back to the defaults with a warning when they can't be read
+    pub fn load_or_default() -> Settings {
+        Self::load().unwrap_or_else(|e| {
+            tracing::warn!("Error loading settings, using the defaults: {}", e);
+            Settings::default()
+        })
+    }
+
+    /// Checks the values entered in the settings, reporting every invalid field
+    pub fn validate(&self) -> Result<(), AppError> {
+        let mut fields = Vec::new();
+
+        for (field, value) in [
+            ("hp_drain_rate", self.hp_drain_rate),
+            ("overall_difficulty", self.overall_difficulty),
+        ] {
+            if !(0.0..=10.0).contains(&value) {
+                fields.push(FieldError::new(field, "Must be between 0 and 10"));
+            }
+        }
+        for (field, path) in [
+            ("song_path", &self.song_path),
+            ("downloads_path", &self.downloads_path),
+        ] {
+            // Empty paths fall back to the default directories
+            if path.is_empty() {
+                continue;
+            }
+            if let Err(message) = check_writable_dir(Path::new(path)) {
+                fields.push(FieldError::new(field, message));
+            }
+        }
+        for (field, rate) in [("rate_min", self.rate_min), ("rate_max", self.rate_max)] {
+            if !RATE_RANGE.contains(&rate) {
+                fields.push(FieldError::new(
+                    field,
+                    format!(
+                        "Must be between {} and {}",
+                        RATE_RANGE.start(),
+                        RATE_RANGE.end()
+                    ),
+                ));
+            }
+        }
+        if self.rate_min > self.rate_max {
+            fields.push(FieldError::new(
+                "rate_max",
+                "Must not be lower than the minimum rate",
+            ));
+        }
+        if self.rate_step.is_nan() || self.rate_step <= 0.0 {
+            fields.push(FieldError::new("rate_step", "Must be greater than 0"));
+        } else if self.rate_min <= self.rate_max {
+            let count = rate_count(self.rate_min, self.rate_max, self.rate_step);
+            if count > MAX_RATE_COUNT as f64 {
+                fields.push(FieldError::new(
+                    "rate_step",
+                    format!("Generates {} rates, the limit is {}", count, MAX_RATE_COUNT),
+                ));
+            }
+        }
+        if self.max_concurrent_downloads == 0 {
+            fields.push(FieldError::new(
+                "max_concurrent_downloads",
+                "Must be at least 1",
+            ));
+        }
+        if !self.api_url.is_empty() {
+            match reqwest::Url::parse(&self.api_url) {
+                Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+                Ok(_) => fields.push(FieldError::new("api_url", "Must be an http or https URL")),
+                Err(e) => fields.push(FieldError::new("api_url", format!("Invalid URL: {}", e))),
+            }
+        }
+
+        if fields.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::new(ErrorKind::Settings, "Some settings are invalid").with_fields(fields))
+        }
+    }
+
+    pub fn save(&self) -> Result<(), AppError> {
         let config_path = Self::get_config_path()?;
-        let content = serde_json::to_string_pretty(self)
-            .map_err(|e| format!("Error serializing settings: {}", e))?;
-        
+        let content = serde_json::to_string_pretty(self).map_err(|e| {
+            AppError::new(
+                ErrorKind::Settings,
+                format!("Error serializing settings: {}", e),
+            )
+        })?;
+
         fs::write(&config_path, content)
-            .map_err(|e| format!("Error writing settings file: {}", e))?;
-        
+            .map_err(|e| AppError::io("Error writing settings file", e))?;
+
         Ok(())
     }
 }
 
+/// Upgrades a settings file to the current version, returns whether it changed
+fn migrate(value: &mut serde_json::Value) -> bool {
+    // Anything but an object is reported by the deserialization
+    let Some(object) = value.as_object_mut() else {
+        return false;
+    };
+
+    let version = object
+        .get("version")
+        .and_then(|version| version.as_u64())
+        .unwrap_or(0) as u32;
+    if version > SETTINGS_VERSION {
+        tracing::warn!(
+            "Settings file version {} is newer than {}, unknown fields are ignored",
+            version,
+            SETTINGS_VERSION
+        );
+        return false;
+    }
+
+    for step in &MIGRATIONS[version as usize..] {
+        step(object);
+    }
+    object.insert("version".to_string(), SETTINGS_VERSION.into());
+    tracing::debug!(
+        "Migrated settings from version {} to {}",
+        version,
+        SETTINGS_VERSION
+    );
+
+    version < SETTINGS_VERSION
+}
+
+/// Files written before the version field, the fields added since take their defaults
+fn from_unversioned(_settings: &mut serde_json::Map<String, serde_json::Value>) {}
+
+/// Fails with the reason the directory can't be used to write files
+fn check_writable_dir(dir: &Path) -> Result<(), String> {
+    if !dir.is_dir() {
+        return Err("Directory does not exist".to_string());
+    }
+
+    let probe = dir.join(".rotterna-write-test");
+    fs::write(&probe, b"").map_err(|e| format!("Directory is not writable: {}", e))?;
+    let _ = fs::remove_file(&probe);
+    Ok(())
+}
+
 #[tauri::command]
-pub fn get_settings() -> Result<Settings, String> {
+pub fn get_settings() -> Result<Settings, AppError> {
     Settings::load()
 }
 
 #[tauri::command]
-pub fn set_settings(settings: Settings) -> Result<(), String> {
-    settings.save()
+#[tracing::instrument(skip_all)]
+pub fn set_settings(settings: Settings) -> Result<(), AppError> {
+    settings.validate()?;
+    settings.save()?;
+    crate::logging::set_level(settings.log_level);
+    Ok(())
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unversioned_files_are_migrated_with_defaults() {
+        let mut value = serde_json::json!({
+            "hp_drain_rate": 7.0,
+            "overall_difficulty": 8.5,
+            "song_path": ""
+        });
+
+        assert!(migrate(&mut value));
+        let settings: Settings = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.hp_drain_rate, 7.0);
+        assert_eq!(settings.max_concurrent_downloads, 2);
+        assert_eq!(settings.extract_limits, ExtractLimits::default());
+
+        // Already up to date
+        assert!(!migrate(&mut value));
+    }
+
+    #[test]
+    fn validation_reports_every_invalid_field() {
+        let settings = Settings {
+            hp_drain_rate: 11.0,
+            overall_difficulty: -1.0,
+            song_path: "/does/not/exist".to_string(),
+            downloads_path: std::env::temp_dir().to_string_lossy().to_string(),
+            ..Settings::default()
+        };
+
+        let error = settings.validate().unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Settings);
+        let fields: Vec<_> = error.fields.iter().map(|f| f.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            vec!["hp_drain_rate", "overall_difficulty", "song_path"]
+        );
+
+        let fields = |settings: Settings| -> Vec<String> {
+            settings.validate().map_or_else(
+                |error| error.fields.into_iter().map(|f| f.field).collect(),
+                |_| Vec::new(),
+            )
+        };
+        let rates = |rate_min, rate_max, rate_step| Settings {
+            rate_min,
+            rate_max,
+            rate_step,
+            ..Settings::default()
+        };
+        assert_eq!(fields(rates(0.0, 1.5, 0.1)), vec!["rate_min"]);
+        assert_eq!(fields(rates(1.5, 1.2, 0.1)), vec!["rate_max"]);
+        assert_eq!(fields(rates(0.5, 200.0, 10.0)), vec!["rate_max"]);
+        assert_eq!(fields(rates(0.5, 2.0, 0.0001)), vec!["rate_step"]);
+        assert!(fields(rates(0.5, 2.0, 0.05)).is_empty());
+
+        assert!(Settings::default().validate().is_ok());
+    }
+}
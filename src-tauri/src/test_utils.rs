@@ -0,0 +1,57 @@
+//! Helpers shared by the unit tests
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Simfile with a single 4K chart
+pub const SIMPLE_SM: &str = "#TITLE:Test Song;
+#ARTIST:Test Artist;
+#CREDIT:Tester;
+#MUSIC:audio.ogg;
+#OFFSET:0.000;
+#BPMS:0.000=120.000;
+#STOPS:;
+#NOTES:
+     dance-single:
+     :
+     Hard:
+     10:
+     0,0,0,0,0:
+1000
+0100
+0010
+0001
+;
+";
+
+/// Empty directory under the system temp directory, removed on drop even when the test panics
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "rotterna-{}-{}-{}",
+            name,
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+}
+
+impl std::ops::Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
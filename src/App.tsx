@@ -1,9 +1,12 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { Select } from "rsc-daisyui";
-import { usePacks } from "./hooks/usePacks";
-import { PackCard } from "./components/PackCard";
+import { usePacks, type SortDirection, type SortKey } from "./hooks/usePacks";
+import { PackCard, QueueEntry } from "./components/PackCard";
 import { Settings } from "./components/Settings";
+import { EMPTY_FILTERS, PackFiltersPanel, type PackFilters } from "./components/PackFilters";
+import { errorRemedy, isRetryable } from "./errors";
 import "./App.css";
 
 interface SortOption {
@@ -11,25 +14,60 @@ interface SortOption {
   label: string;
 }
 
+interface SortOptions {
+  fields: SortOption[];
+  directions: SortOption[];
+}
+
+interface CatalogStatus {
+  synced_at: number | null;
+  pack_count: number;
+  syncing: boolean;
+  synced_pages: number;
+  total_pages: number;
+}
+
+interface LibraryPack {
+  id: number;
+  name: string;
+  install_path: string;
+  installed_at: number;
+}
+
 function App() {
   const [page, setPage] = useState(1);
   const [search, setSearch] = useState("");
   const [sortField, setSortField] = useState("name");
-  const [sortOrder, setSortOrder] = useState<"asc" | "desc">("asc");
-  const [sortOptions, setSortOptions] = useState<SortOption[]>([]);
+  const [sortOrder, setSortOrder] = useState<SortDirection>("ascending");
+  const [thenField, setThenField] = useState("");
+  const [thenOrder, setThenOrder] = useState<SortDirection>("ascending");
+  const [sortOptions, setSortOptions] = useState<SortOptions>({ fields: [], directions: [] });
+  const [filters, setFilters] = useState<PackFilters>(EMPTY_FILTERS);
+  const [showFilters, setShowFilters] = useState(false);
   const [showSettings, setShowSettings] = useState(false);
   const [downloading, setDownloading] = useState<Set<number>>(new Set());
   const [downloaded, setDownloaded] = useState<Set<number>>(new Set());
+  const [queue, setQueue] = useState<QueueEntry[]>([]);
+  const [catalog, setCatalog] = useState<CatalogStatus | null>(null);
   
-  // Build sort string: "-field" for descending, "field" for ascending
-  const sortString = sortOrder === "desc" ? `-${sortField}` : sortField;
-  const { packs, loading, error } = usePacks({ page, limit: 12, sort: sortString, search });
+  const sort: SortKey[] = [
+    { field: sortField, direction: sortOrder },
+    ...(thenField && thenField !== sortField ? [{ field: thenField, direction: thenOrder }] : []),
+  ];
+  const { packs, loading, error, meta, retry } = usePacks({
+    page,
+    limit: 12,
+    sort,
+    search,
+    filters,
+    source: catalog && catalog.pack_count > 0 ? "catalog" : "online",
+  });
   
   // Load sort options on startup
   useEffect(() => {
     const loadSortOptions = async () => {
       try {
-        const options = await invoke<SortOption[]>("get_sort_options");
+        const options = await invoke<SortOptions>("get_sort_options");
         setSortOptions(options);
       } catch (err) {
         console.error("[App] Error loading sort options:", err);
@@ -38,6 +76,56 @@ function App() {
     loadSortOptions();
   }, []);
   
+  // Load installed packs from the library
+  const loadLibrary = async () => {
+    try {
+      const library = await invoke<LibraryPack[]>("list_library");
+      setDownloaded(new Set(library.map((p) => p.id)));
+    } catch (err) {
+      console.error("[App] Error loading library:", err);
+    }
+  };
+  
+  useEffect(() => {
+    loadLibrary();
+  }, []);
+  
+  // Keep the download queue in sync with the backend
+  useEffect(() => {
+    invoke<QueueEntry[]>("get_download_queue")
+      .then(setQueue)
+      .catch((err) => console.error("[App] Error loading download queue:", err));
+    
+    const unlisten = listen<QueueEntry[]>("download-queue", (event) => {
+      setQueue(event.payload);
+    });
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, []);
+  
+  // Follow the catalog sync started in the background
+  useEffect(() => {
+    invoke<CatalogStatus>("get_catalog_status")
+      .then(setCatalog)
+      .catch((err) => console.error("[App] Error loading catalog status:", err));
+    
+    const unlisten = listen<CatalogStatus>("catalog-status", (event) => {
+      setCatalog(event.payload);
+    });
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, []);
+  
+  const handleSyncCatalog = async () => {
+    try {
+      setCatalog(await invoke<CatalogStatus>("sync_catalog"));
+    } catch (err) {
+      console.error("[App] Error syncing catalog:", err);
+    }
+  };
+  
   // Clean up downloads state when packs change (e.g., page change)
   useEffect(() => {
     // Keep only downloads for packs that are still in the current list
@@ -55,12 +143,30 @@ function App() {
     <main className="container mx-auto p-4">
       <div className="flex justify-between items-center mb-6">
         <h1 className="text-4xl font-bold">Etterna Packs</h1>
-        <button
-          className="btn btn-outline btn-sm"
-          onClick={() => setShowSettings(true)}
-        >
-          Settings
-        </button>
+        <div className="flex items-center gap-2">
+          {catalog && (
+            <span className="text-sm opacity-70">
+              {catalog.syncing
+                ? `Syncing catalog ${catalog.synced_pages}/${catalog.total_pages || "?"}`
+                : catalog.synced_at
+                  ? `${catalog.pack_count} packs, synced ${new Date(catalog.synced_at * 1000).toLocaleString()}`
+                  : "Catalog not synced"}
+            </span>
+          )}
+          <button
+            className="btn btn-outline btn-sm"
+            onClick={handleSyncCatalog}
+            disabled={catalog?.syncing}
+          >
+            Sync
+          </button>
+          <button
+            className="btn btn-outline btn-sm"
+            onClick={() => setShowSettings(true)}
+          >
+            Settings
+          </button>
+        </div>
       </div>
 
       <div className="mb-6 flex gap-2 items-center flex-wrap">
@@ -86,7 +192,7 @@ function App() {
           className="select-bordered"
         >
           <option value="" disabled>Sort by</option>
-          {sortOptions.map((option) => (
+          {sortOptions.fields.map((option) => (
             <option key={option.value} value={option.value}>
               {option.label}
             </option>
@@ -96,15 +202,70 @@ function App() {
         <Select
           value={sortOrder}
           onChange={(e) => {
-            setSortOrder(e.target.value as "asc" | "desc");
+            setSortOrder(e.target.value as SortDirection);
             setPage(1);
           }}
           className="select-bordered"
         >
-          <option value="asc">Ascending</option>
-          <option value="desc">Descending</option>
+          {sortOptions.directions.map((option) => (
+            <option key={option.value} value={option.value}>
+              {option.label}
+            </option>
+          ))}
+        </Select>
+        
+        <Select
+          value={thenField}
+          onChange={(e) => {
+            setThenField(e.target.value);
+            setPage(1);
+          }}
+          className="select-bordered"
+        >
+          <option value="">Then by</option>
+          {sortOptions.fields
+            .filter((option) => option.value !== sortField)
+            .map((option) => (
+              <option key={option.value} value={option.value}>
+                {option.label}
+              </option>
+            ))}
         </Select>
+        
+        {thenField && (
+          <Select
+            value={thenOrder}
+            onChange={(e) => {
+              setThenOrder(e.target.value as SortDirection);
+              setPage(1);
+            }}
+            className="select-bordered"
+          >
+            {sortOptions.directions.map((option) => (
+              <option key={option.value} value={option.value}>
+                {option.label}
+              </option>
+            ))}
+          </Select>
+        )}
+        
+        <button
+          className={`btn ${showFilters ? "btn-active" : "btn-outline"}`}
+          onClick={() => setShowFilters((prev) => !prev)}
+        >
+          Filters
+        </button>
       </div>
+      
+      {showFilters && (
+        <PackFiltersPanel
+          filters={filters}
+          onApply={(applied) => {
+            setFilters(applied);
+            setPage(1);
+          }}
+        />
+      )}
 
       {loading && (
         <div className="flex justify-center">
@@ -113,7 +274,15 @@ function App() {
       )}
       {error && (
         <div className="alert alert-error">
-          <span>Error: {error}</span>
+          <div className="flex flex-col">
+            <span>Error: {error.message}</span>
+            {errorRemedy(error) && <span className="text-sm">{errorRemedy(error)}</span>}
+          </div>
+          {isRetryable(error) && (
+            <button className="btn btn-sm" onClick={retry}>
+              Retry
+            </button>
+          )}
       </div>
       )}
 
@@ -126,6 +295,8 @@ function App() {
                 pack={pack}
                 isDownloading={downloading.has(pack.id)}
                 isDownloaded={downloaded.has(pack.id)}
+                queueEntry={queue.find((entry) => entry.pack_id === pack.id)}
+                queuePosition={queue.findIndex((entry) => entry.pack_id === pack.id)}
                 onDownloadStart={() => {
                   setDownloading((prev) => new Set(prev).add(pack.id));
                 }}
@@ -136,6 +307,7 @@ function App() {
                     return newSet;
                   });
                   setDownloaded((prev) => new Set(prev).add(pack.id));
+                  loadLibrary();
                 }}
                 onDownloadError={() => {
                   setDownloading((prev) => {
@@ -144,10 +316,23 @@ function App() {
                     return newSet;
                   });
                 }}
+                onUninstall={loadLibrary}
               />
             ))}
           </div>
 
+          {meta && meta.totals_are_estimates && (
+            <div className="alert alert-info mb-4">
+              <span>
+                {meta.filtered_out > 0
+                  ? `${meta.filtered_out} packs of this page are hidden by the filters. `
+                  : ""}
+                Some filters are applied page by page, so pages can be short. Sync the catalog
+                for exact results.
+              </span>
+            </div>
+          )}
+
           <div className="flex justify-center items-center gap-4">
             <button
               className="btn btn-outline"
@@ -156,8 +341,15 @@ function App() {
             >
               Previous
             </button>
-            <span>Page {page}</span>
-            <button className="btn btn-outline" onClick={() => setPage((p) => p + 1)}>
+            <span>
+              Page {page}
+              {meta && !meta.totals_are_estimates && ` of ${meta.last_page}`}
+            </span>
+            <button
+              className="btn btn-outline"
+              onClick={() => setPage((p) => p + 1)}
+              disabled={meta !== null && page >= meta.last_page}
+            >
               Next
             </button>
           </div>
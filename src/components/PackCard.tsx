@@ -4,32 +4,90 @@ import { useState, useRef, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import type { Pack } from "../hooks/usePacks";
+import { errorRemedy, toAppError, type AppError } from "../errors";
+import { PackSongs } from "./PackSongs";
+
+export interface QueueEntry {
+  pack_id: number;
+  pack_name: string | null;
+  state: "queued" | "active" | "paused";
+}
+
+export interface ConversionFailure {
+  file: string;
+  reason: "decode" | "encode" | "audio" | "io";
+  message: string;
+}
+
+export interface SkippedEntry {
+  name: string;
+  reason: string;
+}
+
+export interface SongFolderConflict {
+  name: string;
+  // Null when the folder was left out
+  installed_as: string | null;
+}
+
+export interface InstallReport {
+  archive_path: string;
+  // Whether the archive matches the one the pack was last installed from
+  archive_status: "new" | "identical" | "changed";
+  songs_found: number;
+  songs_converted: number;
+  charts_converted: number;
+  files_written: number;
+  failures: ConversionFailure[];
+  skipped_entries: SkippedEntry[];
+  song_folder_conflicts: SongFolderConflict[];
+}
 
 interface PackCardProps {
   pack: Pack;
   isDownloading: boolean;
   isDownloaded: boolean;
+  queueEntry?: QueueEntry;
+  queuePosition: number;
   onDownloadStart: () => void;
   onDownloadComplete: () => void;
   onDownloadError: () => void;
+  onUninstall: () => void;
 }
 
 interface DownloadProgress {
   packId: number;
   downloaded: number;
   total: number;
-  stage: "downloading" | "extracting" | "converting";
+  stage:
+    | "queued"
+    | "downloading"
+    | "paused"
+    | "verifying"
+    | "extracting"
+    | "converting"
+    | "cancelled";
+  // Bytes written so far while extracting
+  bytes?: number;
+  // Title of the song being converted
+  current?: string;
 }
 
 export function PackCard({
   pack,
   isDownloading: externalIsDownloading,
   isDownloaded,
+  queueEntry,
+  queuePosition,
   onDownloadStart,
   onDownloadComplete,
   onDownloadError,
+  onUninstall,
 }: PackCardProps) {
   const [progress, setProgress] = useState<DownloadProgress | null>(null);
+  const [report, setReport] = useState<InstallReport | null>(null);
+  const [downloadError, setDownloadError] = useState<AppError | null>(null);
+  const [showSongs, setShowSongs] = useState(false);
   const unlistenRef = useRef<(() => void) | null>(null);
 
   // Re-initialize listener if download is in progress when component mounts
@@ -68,6 +126,7 @@ export function PackCard({
 
     try {
       onDownloadStart();
+      setDownloadError(null);
       setProgress({ packId: pack.id, downloaded: 0, total: 0, stage: "downloading" });
 
       // Listen for progress events
@@ -80,11 +139,12 @@ export function PackCard({
       unlistenRef.current = unlisten;
 
       console.log("[PackCard] Starting download for pack:", pack.id, pack.download);
-      const filePath = await invoke<string>("download_pack", {
+      const installReport = await invoke<InstallReport>("download_pack", {
         downloadUrl: pack.download,
         packId: pack.id,
+        packName: pack.name,
       });
-      console.log("[PackCard] Download completed:", filePath);
+      setReport(installReport);
 
       // Clean up listener
       if (unlistenRef.current) {
@@ -96,6 +156,10 @@ export function PackCard({
       onDownloadComplete();
     } catch (err) {
       console.error("[PackCard] Download error:", err);
+      const error = toAppError(err);
+      if (error.kind !== "cancelled") {
+        setDownloadError(error);
+      }
       setProgress(null);
       if (unlistenRef.current) {
         unlistenRef.current();
@@ -105,12 +169,54 @@ export function PackCard({
     }
   };
 
+  const handleCancel = async () => {
+    try {
+      await invoke<boolean>("cancel_download", { packId: pack.id });
+    } catch (err) {
+      console.error("[PackCard] Cancel error:", err);
+    }
+  };
+
+  const handleQueueAction = async (command: string, args: Record<string, number> = {}) => {
+    try {
+      await invoke(command, { packId: pack.id, ...args });
+    } catch (err) {
+      console.error(`[PackCard] ${command} error:`, err);
+    }
+  };
+
+  const handleUninstall = async () => {
+    if (!window.confirm(`Uninstall ${pack.name}?`)) {
+      return;
+    }
+
+    try {
+      await invoke("uninstall_pack", { packId: pack.id });
+      onUninstall();
+    } catch (err) {
+      console.error("[PackCard] Uninstall error:", err);
+    }
+  };
+
   const progressPercent = progress
     ? progress.total > 0
       ? Math.round((progress.downloaded / progress.total) * 100)
       : 0
     : 0;
 
+  // Downloaded/total count entries while extracting and songs while converting
+  const progressLabel = progress
+    ? progress.stage === "extracting" && progress.total > 0
+      ? `Extracting ${progress.downloaded}/${progress.total} files (${(
+          (progress.bytes ?? 0) / 1_048_576
+        ).toFixed(1)} MB)`
+      : progress.stage === "converting" && progress.total > 0
+        ? `Converting ${progress.downloaded}/${progress.total} songs${
+            progress.current ? `: ${progress.current}` : ""
+          }`
+        : progress.stage.charAt(0).toUpperCase() + progress.stage.slice(1)
+    : "";
+
   return (
     <div
       className="card border-2 border-primary shadow-lg relative overflow-hidden h-full bg-transparent"
@@ -137,11 +243,14 @@ export function PackCard({
         <div className="flex gap-2 mb-2">
           <div 
             className="tooltip tooltip-bottom"
-            data-tip={`${pack.song_count} songs`}
+            data-tip={`${pack.song_count} songs, click to list them`}
           >
-            <div className="badge badge-outline badge-secondary whitespace-nowrap">
+            <button
+              className="badge badge-outline badge-secondary whitespace-nowrap cursor-pointer"
+              onClick={() => setShowSongs(true)}
+            >
               {pack.song_count}
-            </div>
+            </button>
           </div>
           <div 
             className="tooltip tooltip-bottom"
@@ -159,6 +268,58 @@ export function PackCard({
               {pack.overall.toFixed(2)}
             </div>
           </div>
+          {downloadError && (
+            <div
+              className="tooltip tooltip-bottom"
+              data-tip={[downloadError.message, errorRemedy(downloadError)]
+                .filter(Boolean)
+                .join("\n")}
+            >
+              <div className="badge badge-error whitespace-nowrap">Download failed</div>
+            </div>
+          )}
+          {report && (
+            <div
+              className="tooltip tooltip-bottom"
+              data-tip={
+                report.failures.length > 0 ||
+                report.skipped_entries.length > 0 ||
+                report.song_folder_conflicts.length > 0
+                  ? [
+                      ...report.failures.map(
+                        (failure) => `${failure.file} (${failure.reason}): ${failure.message}`
+                      ),
+                      ...report.skipped_entries.map(
+                        (entry) => `${entry.name}: not extracted (${entry.reason})`
+                      ),
+                      ...report.song_folder_conflicts.map((conflict) =>
+                        conflict.installed_as
+                          ? `${conflict.name}: folder exists, installed as ${conflict.installed_as}`
+                          : `${conflict.name}: folder exists, not installed`
+                      ),
+                    ].join("\n")
+                  : `${report.charts_converted} charts, ${report.files_written} files written`
+              }
+            >
+              <div
+                className={`badge whitespace-nowrap ${
+                  report.failures.length > 0 ? "badge-error" : "badge-success"
+                }`}
+              >
+                {report.songs_converted}/{report.songs_found} converted
+                {report.failures.length > 0 && `, ${report.failures.length} failed`}
+                {report.skipped_entries.length > 0 &&
+                  `, ${report.skipped_entries.length} files skipped`}
+                {report.song_folder_conflicts.length > 0 &&
+                  `, ${report.song_folder_conflicts.length} folder conflicts`}
+              </div>
+            </div>
+          )}
+          {report && report.archive_status !== "new" && (
+            <div className="badge badge-ghost whitespace-nowrap">
+              {report.archive_status === "identical" ? "Same archive as installed" : "Archive updated"}
+            </div>
+          )}
         </div>
 
         {/* Bottom row: tags, plays, and download button */}
@@ -185,8 +346,39 @@ export function PackCard({
             </div>
           </div>
           
-          {externalIsDownloading && progress ? (
-            <div className="flex items-center gap-2">
+          {externalIsDownloading && queueEntry && queueEntry.state !== "active" ? (
+            <div className="flex items-center gap-1">
+              <span className="text-white text-xs drop-shadow-lg">
+                {queueEntry.state === "paused" ? "Paused" : `Queued #${queuePosition + 1}`}
+              </span>
+              {queueEntry.state === "queued" && queuePosition > 0 && (
+                <button
+                  className="btn btn-ghost btn-xs btn-circle text-white"
+                  onClick={() => handleQueueAction("move_download", { position: queuePosition - 1 })}
+                  title="Move up in queue"
+                >
+                  ↑
+                </button>
+              )}
+              <button
+                className="btn btn-ghost btn-xs btn-circle text-white"
+                onClick={() =>
+                  handleQueueAction(queueEntry.state === "paused" ? "resume_download" : "pause_download")
+                }
+                title={queueEntry.state === "paused" ? "Resume" : "Pause"}
+              >
+                {queueEntry.state === "paused" ? "▶" : "⏸"}
+              </button>
+              <button
+                className="btn btn-ghost btn-xs btn-circle text-white"
+                onClick={handleCancel}
+                title="Cancel download"
+              >
+                ✕
+              </button>
+            </div>
+          ) : externalIsDownloading && progress ? (
+            <div className="flex items-center gap-2" title={progressLabel}>
               <span className="text-white text-xs drop-shadow-lg">
                 {progressPercent}%
               </span>
@@ -195,12 +387,28 @@ export function PackCard({
                 value={progressPercent}
                 max="100"
               ></progress>
+              {progress.stage === "downloading" && (
+                <button
+                  className="btn btn-ghost btn-xs btn-circle text-white"
+                  onClick={() => handleQueueAction("pause_download")}
+                  title="Pause"
+                >
+                  ⏸
+                </button>
+              )}
+              <button
+                className="btn btn-ghost btn-xs btn-circle text-white"
+                onClick={handleCancel}
+                title="Cancel download"
+              >
+                ✕
+              </button>
             </div>
           ) : isDownloaded ? (
             <button
               className="btn btn-success btn-sm btn-circle"
-              disabled
-              title="Downloaded"
+              onClick={handleUninstall}
+              title="Downloaded - click to uninstall"
             >
               <svg
                 xmlns="http://www.w3.org/2000/svg"
@@ -242,6 +450,8 @@ export function PackCard({
           )}
         </div>
       </div>
+
+      {showSongs && <PackSongs pack={pack} onClose={() => setShowSongs(false)} />}
     </div>
   );
 }
@@ -0,0 +1,211 @@
+"use client";
+
+import { useState } from "react";
+
+export interface ValueRange {
+  min?: number;
+  max?: number;
+}
+
+// Mirrors PackFilters in src-tauri/src/packs/filters.rs
+export interface PackFilters {
+  msd: Record<string, ValueRange>;
+  include_tags: string[];
+  exclude_tags: string[];
+  exclude_nsfw: boolean;
+  song_count: ValueRange;
+  size_mb: ValueRange;
+}
+
+export const EMPTY_FILTERS: PackFilters = {
+  msd: {},
+  include_tags: [],
+  exclude_tags: [],
+  exclude_nsfw: false,
+  song_count: {},
+  size_mb: {},
+};
+
+const SKILLSETS = [
+  "overall",
+  "stream",
+  "jumpstream",
+  "handstream",
+  "jacks",
+  "chordjacks",
+  "stamina",
+  "technical",
+];
+
+interface PackFiltersProps {
+  filters: PackFilters;
+  onApply: (filters: PackFilters) => void;
+}
+
+function parseBound(value: string): number | undefined {
+  const parsed = parseFloat(value);
+  return Number.isNaN(parsed) ? undefined : parsed;
+}
+
+function parseTags(value: string): string[] {
+  return value
+    .split(",")
+    .map((tag) => tag.trim())
+    .filter((tag) => tag.length > 0);
+}
+
+function RangeInputs({
+  range,
+  step,
+  onChange,
+}: {
+  range: ValueRange;
+  step: string;
+  onChange: (range: ValueRange) => void;
+}) {
+  return (
+    <div className="flex gap-2">
+      <input
+        type="number"
+        step={step}
+        min="0"
+        placeholder="Min"
+        className="input input-bordered input-sm w-24"
+        value={range.min ?? ""}
+        onChange={(e) => onChange({ ...range, min: parseBound(e.target.value) })}
+      />
+      <input
+        type="number"
+        step={step}
+        min="0"
+        placeholder="Max"
+        className="input input-bordered input-sm w-24"
+        value={range.max ?? ""}
+        onChange={(e) => onChange({ ...range, max: parseBound(e.target.value) })}
+      />
+    </div>
+  );
+}
+
+// Advanced filters of the pack list, applied together with the search
+export function PackFiltersPanel({ filters, onApply }: PackFiltersProps) {
+  const [draft, setDraft] = useState<PackFilters>(filters);
+  const [skillset, setSkillset] = useState("overall");
+  const [includeTags, setIncludeTags] = useState(filters.include_tags.join(", "));
+  const [excludeTags, setExcludeTags] = useState(filters.exclude_tags.join(", "));
+
+  const setMsdRange = (range: ValueRange) =>
+    setDraft((prev) => {
+      const msd = { ...prev.msd };
+      if (range.min === undefined && range.max === undefined) {
+        delete msd[skillset];
+      } else {
+        msd[skillset] = range;
+      }
+      return { ...prev, msd };
+    });
+
+  const handleApply = () =>
+    onApply({
+      ...draft,
+      include_tags: parseTags(includeTags),
+      exclude_tags: parseTags(excludeTags),
+    });
+
+  const handleReset = () => {
+    setDraft(EMPTY_FILTERS);
+    setIncludeTags("");
+    setExcludeTags("");
+    onApply(EMPTY_FILTERS);
+  };
+
+  return (
+    <div className="card bg-base-200 mb-6">
+      <div className="card-body p-4 grid grid-cols-1 md:grid-cols-2 gap-4">
+        <div className="form-control">
+          <label className="label">
+            <span className="label-text font-semibold">MSD</span>
+          </label>
+          <div className="flex gap-2">
+            <select
+              className="select select-bordered select-sm"
+              value={skillset}
+              onChange={(e) => setSkillset(e.target.value)}
+            >
+              {SKILLSETS.map((name) => (
+                <option key={name} value={name}>
+                  {name.charAt(0).toUpperCase() + name.slice(1)}
+                  {draft.msd[name] ? " *" : ""}
+                </option>
+              ))}
+            </select>
+            <RangeInputs range={draft.msd[skillset] ?? {}} step="0.5" onChange={setMsdRange} />
+          </div>
+        </div>
+
+        <div className="form-control">
+          <label className="label">
+            <span className="label-text font-semibold">Songs</span>
+          </label>
+          <RangeInputs
+            range={draft.song_count}
+            step="1"
+            onChange={(song_count) => setDraft((prev) => ({ ...prev, song_count }))}
+          />
+        </div>
+
+        <div className="form-control">
+          <label className="label">
+            <span className="label-text font-semibold">Tags</span>
+          </label>
+          <div className="flex gap-2">
+            <input
+              type="text"
+              placeholder="Include (comma separated)"
+              className="input input-bordered input-sm flex-1"
+              value={includeTags}
+              onChange={(e) => setIncludeTags(e.target.value)}
+            />
+            <input
+              type="text"
+              placeholder="Exclude"
+              className="input input-bordered input-sm flex-1"
+              value={excludeTags}
+              onChange={(e) => setExcludeTags(e.target.value)}
+            />
+          </div>
+        </div>
+
+        <div className="form-control">
+          <label className="label">
+            <span className="label-text font-semibold">Size (MB)</span>
+          </label>
+          <RangeInputs
+            range={draft.size_mb}
+            step="100"
+            onChange={(size_mb) => setDraft((prev) => ({ ...prev, size_mb }))}
+          />
+        </div>
+
+        <label className="label cursor-pointer justify-start gap-2">
+          <input
+            type="checkbox"
+            className="checkbox checkbox-sm"
+            checked={draft.exclude_nsfw}
+            onChange={(e) => setDraft((prev) => ({ ...prev, exclude_nsfw: e.target.checked }))}
+          />
+          <span className="label-text">Hide NSFW packs</span>
+        </label>
+
+        <div className="flex gap-2 justify-end items-end">
+          <button className="btn btn-ghost btn-sm" onClick={handleReset}>
+            Reset
+          </button>
+          <button className="btn btn-primary btn-sm" onClick={handleApply}>
+            Apply
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+}
@@ -0,0 +1,123 @@
+"use client";
+
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import type { Pack } from "../hooks/usePacks";
+import { toAppError, type AppError } from "../errors";
+
+interface PackChart {
+  difficulty: string;
+  overall: number;
+  stream: number;
+  jumpstream: number;
+  handstream: number;
+  jacks: number;
+  chordjacks: number;
+  stamina: number;
+  technical: number;
+}
+
+interface PackSong {
+  id: number;
+  title: string;
+  artist: string;
+  bpm: number | null;
+  // Length in seconds
+  length: number | null;
+  charts: PackChart[];
+}
+
+interface PackDetails {
+  pack: Pack;
+  songs: PackSong[];
+}
+
+interface PackSongsProps {
+  pack: Pack;
+  onClose: () => void;
+}
+
+function formatLength(seconds: number | null): string {
+  if (seconds === null) {
+    return "-";
+  }
+  const minutes = Math.floor(seconds / 60);
+  return `${minutes}:${String(Math.round(seconds % 60)).padStart(2, "0")}`;
+}
+
+// Song list of a pack, fetched when opened
+export function PackSongs({ pack, onClose }: PackSongsProps) {
+  const [songs, setSongs] = useState<PackSong[] | null>(null);
+  const [error, setError] = useState<AppError | null>(null);
+
+  useEffect(() => {
+    invoke<PackDetails>("fetch_pack_details", { packId: pack.id })
+      .then((details) => setSongs(details.songs))
+      .catch((err) => {
+        console.error("[PackSongs] Error fetching pack details:", err);
+        setError(toAppError(err));
+      });
+  }, [pack.id]);
+
+  return (
+    <dialog className="modal modal-open" onClose={onClose}>
+      <div className="modal-box max-w-4xl">
+        <h3 className="font-bold text-lg mb-4">{pack.name}</h3>
+
+        {error ? (
+          <div className="alert alert-error">{error.message}</div>
+        ) : songs === null ? (
+          <div className="flex justify-center py-8">
+            <span className="loading loading-spinner loading-lg"></span>
+          </div>
+        ) : (
+          <div className="overflow-x-auto max-h-[60vh]">
+            <table className="table table-sm table-pin-rows">
+              <thead>
+                <tr>
+                  <th>Title</th>
+                  <th>Artist</th>
+                  <th>BPM</th>
+                  <th>Length</th>
+                  <th>Charts (MSD)</th>
+                </tr>
+              </thead>
+              <tbody>
+                {songs.map((song) => (
+                  <tr key={song.id}>
+                    <td>{song.title}</td>
+                    <td>{song.artist}</td>
+                    <td>{song.bpm !== null ? Math.round(song.bpm) : "-"}</td>
+                    <td>{formatLength(song.length)}</td>
+                    <td>
+                      <div className="flex flex-wrap gap-1">
+                        {song.charts.map((chart, idx) => (
+                          <div
+                            key={idx}
+                            className="tooltip tooltip-left"
+                            data-tip={`Stream ${chart.stream.toFixed(2)}, Jumpstream ${chart.jumpstream.toFixed(2)}, Handstream ${chart.handstream.toFixed(2)}, Jacks ${chart.jacks.toFixed(2)}, Chordjacks ${chart.chordjacks.toFixed(2)}, Stamina ${chart.stamina.toFixed(2)}, Technical ${chart.technical.toFixed(2)}`}
+                          >
+                            <div className="badge badge-outline badge-warning badge-sm whitespace-nowrap">
+                              {chart.difficulty} {chart.overall.toFixed(2)}
+                            </div>
+                          </div>
+                        ))}
+                      </div>
+                    </td>
+                  </tr>
+                ))}
+              </tbody>
+            </table>
+          </div>
+        )}
+
+        <div className="modal-action">
+          <button className="btn btn-sm" onClick={onClose}>
+            Close
+          </button>
+        </div>
+      </div>
+      <div className="modal-backdrop" onClick={onClose}></div>
+    </dialog>
+  );
+}
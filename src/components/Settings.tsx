@@ -2,12 +2,34 @@
 
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
-import { open } from "@tauri-apps/plugin-dialog";
+import { open, save } from "@tauri-apps/plugin-dialog";
+import { toAppError } from "../errors";
 
 interface Settings {
+  version: number;
   hp_drain_rate: number;
   overall_difficulty: number;
   song_path: string;
+  downloads_path: string;
+  rate_min: number;
+  rate_max: number;
+  rate_step: number;
+  ffmpeg_path: string;
+  max_concurrent_downloads: number;
+  conversion_threads: number;
+  extract_limits: ExtractLimits;
+  log_level: LogLevel;
+  api_url: string;
+}
+
+type LogLevel = "error" | "warn" | "info" | "debug" | "trace";
+
+const LOG_LEVELS: LogLevel[] = ["error", "warn", "info", "debug", "trace"];
+
+interface ExtractLimits {
+  max_total_size_mb: number;
+  max_entries: number;
+  max_compression_ratio: number;
 }
 
 interface SettingsProps {
@@ -16,12 +38,28 @@ interface SettingsProps {
 
 export function Settings({ onBack }: SettingsProps = {}) {
   const [settings, setSettings] = useState<Settings>({
+    version: 1,
     hp_drain_rate: 8.0,
     overall_difficulty: 9.0,
     song_path: "",
+    downloads_path: "",
+    rate_min: 1.0,
+    rate_max: 1.0,
+    rate_step: 0.1,
+    ffmpeg_path: "ffmpeg",
+    max_concurrent_downloads: 2,
+    conversion_threads: 0,
+    extract_limits: {
+      max_total_size_mb: 16384,
+      max_entries: 100000,
+      max_compression_ratio: 200,
+    },
+    log_level: "info",
+    api_url: "",
   });
   const [loading, setLoading] = useState(true);
   const [saving, setSaving] = useState(false);
+  const [fieldErrors, setFieldErrors] = useState<Record<string, string>>({});
 
   useEffect(() => {
     loadSettings();
@@ -41,11 +79,18 @@ export function Settings({ onBack }: SettingsProps = {}) {
   const handleSave = async () => {
     try {
       setSaving(true);
+      setFieldErrors({});
       await invoke("set_settings", { settings });
       alert("Settings saved successfully!");
     } catch (err) {
       console.error("[Settings] Error saving settings:", err);
-      alert(`Error saving settings: ${err instanceof Error ? err.message : String(err)}`);
+      const error = toAppError(err);
+      if (error.fields && error.fields.length > 0) {
+        setFieldErrors(
+          Object.fromEntries(error.fields.map((f) => [f.field, f.message]))
+        );
+      }
+      alert(`Error saving settings: ${error.message}`);
     } finally {
       setSaving(false);
     }
@@ -66,6 +111,45 @@ export function Settings({ onBack }: SettingsProps = {}) {
     }
   };
 
+  const handleSelectDownloadsPath = async () => {
+    try {
+      const selected = await open({
+        directory: true,
+        multiple: false,
+        title: "Select Downloads Directory",
+      });
+      if (selected && typeof selected === "string") {
+        setSettings((prev) => ({ ...prev, downloads_path: selected }));
+      }
+    } catch (err) {
+      console.error("[Settings] Error selecting downloads path:", err);
+    }
+  };
+
+  const handleExportLogs = async () => {
+    try {
+      const destination = await save({
+        title: "Export Logs",
+        defaultPath: "rotterna-logs.txt",
+      });
+      if (!destination) {
+        return;
+      }
+      const written = await invoke<string>("export_logs", { destination });
+      alert(`Logs exported to ${written}`);
+    } catch (err) {
+      console.error("[Settings] Error exporting logs:", err);
+      alert(`Error exporting logs: ${toAppError(err).message}`);
+    }
+  };
+
+  const fieldError = (field: string) =>
+    fieldErrors[field] ? (
+      <label className="label">
+        <span className="label-text-alt text-error">{fieldErrors[field]}</span>
+      </label>
+    ) : null;
+
   if (loading) {
     return (
       <div className="flex justify-center items-center h-screen">
@@ -105,6 +189,7 @@ export function Settings({ onBack }: SettingsProps = {}) {
                 }))
               }
             />
+            {fieldError("hp_drain_rate")}
           </div>
 
           <div className="form-control mb-4">
@@ -125,6 +210,7 @@ export function Settings({ onBack }: SettingsProps = {}) {
                 }))
               }
             />
+            {fieldError("overall_difficulty")}
           </div>
 
           <div className="form-control mb-6">
@@ -151,6 +237,7 @@ export function Settings({ onBack }: SettingsProps = {}) {
                 Browse
               </button>
             </div>
+            {fieldError("song_path")}
             <label className="label">
               <span className="label-text-alt">
                 Leave empty to keep songs in the download folder
@@ -158,6 +245,286 @@ export function Settings({ onBack }: SettingsProps = {}) {
             </label>
           </div>
 
+          <div className="form-control mb-6">
+            <label className="label">
+              <span className="label-text font-semibold">Downloads Path</span>
+            </label>
+            <div className="flex gap-2">
+              <input
+                type="text"
+                className="input input-bordered flex-1"
+                placeholder="Path where packs are downloaded and extracted"
+                value={settings.downloads_path}
+                onChange={(e) =>
+                  setSettings((prev) => ({
+                    ...prev,
+                    downloads_path: e.target.value,
+                  }))
+                }
+              />
+              <button
+                className="btn btn-outline"
+                onClick={handleSelectDownloadsPath}
+              >
+                Browse
+              </button>
+            </div>
+            {fieldError("downloads_path")}
+            <label className="label">
+              <span className="label-text-alt">
+                Leave empty to use the app data folder
+              </span>
+            </label>
+          </div>
+
+          <div className="form-control mb-4">
+            <label className="label">
+              <span className="label-text font-semibold">Rates</span>
+            </label>
+            <div className="flex gap-2">
+              <input
+                type="number"
+                step="0.05"
+                min="0.5"
+                max="2"
+                className="input input-bordered flex-1"
+                value={settings.rate_min}
+                onChange={(e) =>
+                  setSettings((prev) => ({
+                    ...prev,
+                    rate_min: parseFloat(e.target.value) || 1,
+                  }))
+                }
+              />
+              <input
+                type="number"
+                step="0.05"
+                min="0.5"
+                max="2"
+                className="input input-bordered flex-1"
+                value={settings.rate_max}
+                onChange={(e) =>
+                  setSettings((prev) => ({
+                    ...prev,
+                    rate_max: parseFloat(e.target.value) || 1,
+                  }))
+                }
+              />
+              <input
+                type="number"
+                step="0.05"
+                min="0.05"
+                max="1"
+                className="input input-bordered flex-1"
+                value={settings.rate_step}
+                onChange={(e) =>
+                  setSettings((prev) => ({
+                    ...prev,
+                    rate_step: parseFloat(e.target.value) || 0.1,
+                  }))
+                }
+              />
+            </div>
+            {fieldError("rate_min")}
+            {fieldError("rate_max")}
+            {fieldError("rate_step")}
+            <label className="label">
+              <span className="label-text-alt">
+                Min, max and step of the rated charts generated on download (1.0 to 1.0 disables rates)
+              </span>
+            </label>
+          </div>
+
+          <div className="form-control mb-6">
+            <label className="label">
+              <span className="label-text font-semibold">ffmpeg Path</span>
+            </label>
+            <input
+              type="text"
+              className="input input-bordered"
+              value={settings.ffmpeg_path}
+              onChange={(e) =>
+                setSettings((prev) => ({
+                  ...prev,
+                  ffmpeg_path: e.target.value,
+                }))
+              }
+            />
+            <label className="label">
+              <span className="label-text-alt">
+                Used to render the audio of rated charts
+              </span>
+            </label>
+          </div>
+
+          <div className="form-control mb-6">
+            <label className="label">
+              <span className="label-text font-semibold">Concurrent Downloads</span>
+            </label>
+            <input
+              type="number"
+              step="1"
+              min="1"
+              max="10"
+              className="input input-bordered"
+              value={settings.max_concurrent_downloads}
+              onChange={(e) =>
+                setSettings((prev) => ({
+                  ...prev,
+                  max_concurrent_downloads: parseInt(e.target.value) || 1,
+                }))
+              }
+            />
+            <label className="label">
+              <span className="label-text-alt">
+                Packs downloaded at the same time, the others wait in the queue
+              </span>
+            </label>
+            {fieldError("max_concurrent_downloads")}
+          </div>
+
+          <div className="form-control mb-6">
+            <label className="label">
+              <span className="label-text font-semibold">Conversion Threads</span>
+            </label>
+            <input
+              type="number"
+              step="1"
+              min="0"
+              max="64"
+              className="input input-bordered"
+              value={settings.conversion_threads}
+              onChange={(e) =>
+                setSettings((prev) => ({
+                  ...prev,
+                  conversion_threads: parseInt(e.target.value) || 0,
+                }))
+              }
+            />
+            <label className="label">
+              <span className="label-text-alt">
+                Simfiles converted at the same time, 0 uses every CPU core
+              </span>
+            </label>
+          </div>
+
+          <div className="form-control mb-6">
+            <label className="label">
+              <span className="label-text font-semibold">Archive Limits</span>
+            </label>
+            <div className="flex gap-2">
+              <input
+                type="number"
+                step="1024"
+                min="1"
+                className="input input-bordered flex-1"
+                value={settings.extract_limits.max_total_size_mb}
+                onChange={(e) =>
+                  setSettings((prev) => ({
+                    ...prev,
+                    extract_limits: {
+                      ...prev.extract_limits,
+                      max_total_size_mb: parseInt(e.target.value) || 1,
+                    },
+                  }))
+                }
+              />
+              <input
+                type="number"
+                step="1000"
+                min="1"
+                className="input input-bordered flex-1"
+                value={settings.extract_limits.max_entries}
+                onChange={(e) =>
+                  setSettings((prev) => ({
+                    ...prev,
+                    extract_limits: {
+                      ...prev.extract_limits,
+                      max_entries: parseInt(e.target.value) || 1,
+                    },
+                  }))
+                }
+              />
+              <input
+                type="number"
+                step="10"
+                min="1"
+                className="input input-bordered flex-1"
+                value={settings.extract_limits.max_compression_ratio}
+                onChange={(e) =>
+                  setSettings((prev) => ({
+                    ...prev,
+                    extract_limits: {
+                      ...prev.extract_limits,
+                      max_compression_ratio: parseInt(e.target.value) || 1,
+                    },
+                  }))
+                }
+              />
+            </div>
+            <label className="label">
+              <span className="label-text-alt">
+                Max extracted size (MB), file count and compression ratio of a pack archive
+              </span>
+            </label>
+          </div>
+
+          <div className="form-control mb-6">
+            <label className="label">
+              <span className="label-text font-semibold">API URL</span>
+            </label>
+            <input
+              type="text"
+              className="input input-bordered"
+              placeholder="https://api.etternaonline.com/api/"
+              value={settings.api_url}
+              onChange={(e) =>
+                setSettings((prev) => ({
+                  ...prev,
+                  api_url: e.target.value,
+                }))
+              }
+            />
+            {fieldError("api_url")}
+            <label className="label">
+              <span className="label-text-alt">
+                EtternaOnline API or a mirror, leave empty for the default (applied on restart)
+              </span>
+            </label>
+          </div>
+
+          <div className="form-control mb-6">
+            <label className="label">
+              <span className="label-text font-semibold">Log Level</span>
+            </label>
+            <div className="flex gap-2">
+              <select
+                className="select select-bordered flex-1"
+                value={settings.log_level}
+                onChange={(e) =>
+                  setSettings((prev) => ({
+                    ...prev,
+                    log_level: e.target.value as LogLevel,
+                  }))
+                }
+              >
+                {LOG_LEVELS.map((level) => (
+                  <option key={level} value={level}>
+                    {level}
+                  </option>
+                ))}
+              </select>
+              <button className="btn btn-outline" onClick={handleExportLogs}>
+                Export Logs
+              </button>
+            </div>
+            <label className="label">
+              <span className="label-text-alt">
+                Verbosity of the log file, export the last days of logs to attach them to a bug report
+              </span>
+            </label>
+          </div>
+
           <div className="card-actions justify-end">
             <button
               className="btn btn-primary"
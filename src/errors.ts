@@ -0,0 +1,71 @@
+// Mirrors AppError in src-tauri/src/error.rs
+export type ErrorKind =
+  | "network"
+  | "rate_limited"
+  | "http"
+  | "invalid_response"
+  | "disk_full"
+  | "io"
+  | "invalid_archive"
+  | "conversion"
+  | "settings"
+  | "invalid_input"
+  | "not_found"
+  | "conflict"
+  | "cancelled"
+  | "paused";
+
+export interface FieldError {
+  field: string;
+  message: string;
+}
+
+export interface AppError {
+  kind: ErrorKind;
+  message: string;
+  details?: string;
+  fields?: FieldError[];
+}
+
+export function isAppError(err: unknown): err is AppError {
+  return (
+    typeof err === "object" &&
+    err !== null &&
+    "kind" in err &&
+    "message" in err
+  );
+}
+
+// Turns anything thrown by invoke into an AppError
+export function toAppError(err: unknown): AppError {
+  if (isAppError(err)) {
+    return err;
+  }
+  return {
+    kind: "io",
+    message: err instanceof Error ? err.message : String(err),
+  };
+}
+
+// Errors that may go away by trying again later
+export function isRetryable(err: AppError): boolean {
+  return err.kind === "network" || err.kind === "rate_limited" || err.kind === "http";
+}
+
+// Short hint shown next to the error message
+export function errorRemedy(err: AppError): string | null {
+  switch (err.kind) {
+    case "network":
+      return "Check your internet connection and try again.";
+    case "rate_limited":
+      return "EtternaOnline is rate limiting requests, wait a moment before retrying.";
+    case "disk_full":
+      return "Free up some disk space and try again.";
+    case "invalid_archive":
+      return "The pack archive is broken or unsupported, try downloading it again.";
+    case "settings":
+      return "Check the values in the settings.";
+    default:
+      return null;
+  }
+}
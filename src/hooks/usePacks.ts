@@ -1,5 +1,7 @@
 import { useState, useEffect, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { toAppError, type AppError } from "../errors";
+import type { PackFilters } from "../components/PackFilters";
 
 export interface Pack {
   id: number;
@@ -34,7 +36,7 @@ export interface PacksResponse {
   };
   meta: {
     current_page: number;
-    from: number;
+    from: number | null;
     last_page: number;
     links: Array<{
       url: string | null;
@@ -43,22 +45,37 @@ export interface PacksResponse {
     }>;
     path: string;
     per_page: number;
-    to: number;
+    to: number | null;
     total: number;
+    // Packs of the page hidden by filters the API doesn't support
+    filtered_out: number;
+    // Set when such filters were applied, total and last_page then also count the hidden packs
+    totals_are_estimates: boolean;
   };
 }
 
+export type SortDirection = "ascending" | "descending";
+
+export interface SortKey {
+  field: string;
+  direction: SortDirection;
+}
+
 interface FetchPacksParams {
   page?: number;
   limit?: number;
-  sort?: string;
+  sort?: SortKey[];
   search?: string;
+  filters?: PackFilters;
+  // The local catalog answers without a network round-trip
+  source?: "catalog" | "online";
 }
 
 export function usePacks(params: FetchPacksParams = {}) {
   const [packs, setPacks] = useState<Pack[]>([]);
   const [loading, setLoading] = useState(true);
-  const [error, setError] = useState<string | null>(null);
+  const [error, setError] = useState<AppError | null>(null);
+  const [attempt, setAttempt] = useState(0);
   const [meta, setMeta] = useState<PacksResponse["meta"] | null>(null);
   
   // Use ref to track previous params and avoid duplicate calls
@@ -67,7 +84,10 @@ export function usePacks(params: FetchPacksParams = {}) {
     page: params.page, 
     limit: params.limit, 
     sort: params.sort, 
-    search: params.search 
+    search: params.search,
+    filters: params.filters,
+    source: params.source,
+    attempt,
   });
 
   useEffect(() => {
@@ -82,11 +102,13 @@ export function usePacks(params: FetchPacksParams = {}) {
         console.log("[usePacks] Fetching packs with params:", params);
         setLoading(true);
         setError(null);
-        const response = await invoke<PacksResponse>("fetch_packs", {
+        const command = params.source === "catalog" ? "search_catalog" : "fetch_packs";
+        const response = await invoke<PacksResponse>(command, {
           page: params.page,
           limit: params.limit,
           sort: params.sort,
           search: params.search,
+          filters: params.filters,
         });
         console.log("[usePacks] Received response:", response);
         console.log("[usePacks] Number of packs:", response.data.length);
@@ -94,7 +116,7 @@ export function usePacks(params: FetchPacksParams = {}) {
         setMeta(response.meta);
       } catch (err) {
         console.error("[usePacks] Error fetching packs:", err);
-        setError(err instanceof Error ? err.message : "Error loading packs");
+        setError(toAppError(err));
       } finally {
         setLoading(false);
       }
@@ -104,6 +126,9 @@ export function usePacks(params: FetchPacksParams = {}) {
     // eslint-disable-next-line react-hooks/exhaustive-deps
   }, [paramsKey]);
 
-  return { packs, loading, error, meta };
+  // Fetches the same page again, after a network error for instance
+  const retry = () => setAttempt((previous) => previous + 1);
+
+  return { packs, loading, error, meta, retry };
 }
 